@@ -1,11 +1,12 @@
 use clap::{Parser, Subcommand};
 use anyhow::Result;
 
-use std::{fmt, path::PathBuf};
+use std::{fmt, path::PathBuf, time::Duration};
 
+use crate::init_progress_bar;
 use crate::command::{
-    init, mark, split, trans, upload, upload_file, remove,
-    InitArgs, MarkArgs, RemoveArgs, SplitArgs, TransArgs, UploadArgs, UploadFileArgs
+    init, mark, split, trans, upload, upload_file, remove, detect, lint, schema, media,
+    DetectArgs, LintArgs, MediaArgs, SchemaArgs, InitArgs, MarkArgs, RemoveArgs, SplitArgs, TransArgs, UploadArgs, UploadFileArgs
 };
 
 // `brew-cli` 客户端参数
@@ -23,6 +24,10 @@ pub struct Cli {
     // #[arg(long, default_value = "sqlx=debug,tower_http=debug,info")]
     #[arg(long, default_value = "tower_http=debug,info")]
     pub rust_log: String,
+
+    /// 单个 ffmpeg/biliup 进程的超时时间（秒）
+    #[arg(long)]
+    pub timeout: Option<u64>,
 }
 
 #[derive(Subcommand, Debug)]
@@ -67,6 +72,26 @@ pub enum Command {
         #[command(flatten)]
         args:  RemoveArgs,
     },
+    /// 检测片头片尾
+    Detect {
+        #[command(flatten)]
+        args:  DetectArgs,
+    },
+    /// 检查媒体配置
+    Lint {
+        #[command(flatten)]
+        args:  LintArgs,
+    },
+    /// 生成配置的 JSON Schema
+    Schema {
+        #[command(flatten)]
+        args:  SchemaArgs,
+    },
+    /// 媒体配置
+    Media {
+        #[command(flatten)]
+        args:  MediaArgs,
+    },
 
 }
 
@@ -80,11 +105,25 @@ impl fmt::Display for Command {
             Command::UploadFile { .. } => write!(f, "upload_file"),
             Command::Mark { .. } => write!(f, "mark"),
             Command::Remove { .. } => write!(f, "remove"),
+            Command::Detect { .. } => write!(f, "detect"),
+            Command::Lint { .. } => write!(f, "lint"),
+            Command::Schema { .. } => write!(f, "schema"),
+            Command::Media { .. } => write!(f, "media"),
         }
     }
 }
 
 pub fn run(cli: Cli) -> Result<()> {
+    bili_video::set_step_timeout(cli.timeout.map(Duration::from_secs));
+
+    // 处理视频的命令展示 ffmpeg 进度
+    if matches!(
+        cli.command,
+        Command::Trans { .. } | Command::Split { .. } | Command::Mark { .. } | Command::Remove { .. } | Command::Detect { .. }
+    ) {
+        init_progress_bar();
+    }
+
     match cli.command {
         Command::Trans { args } => trans(args),
         Command::Split { args } => split(args),
@@ -93,6 +132,10 @@ pub fn run(cli: Cli) -> Result<()> {
         Command::UploadFile { args } => upload_file(args),
         Command::Mark { args } => mark(args),
         Command::Remove { args } => remove(args),
+        Command::Detect { args } => detect(args),
+        Command::Lint { args } => lint(args),
+        Command::Schema { args } => schema(args),
+        Command::Media { args } => media(args),
     }
 }
 
@@ -0,0 +1,164 @@
+//! 检测片头片尾
+//!
+//! ```bash
+//! # 只打印建议
+//! cargo run -- detect "龙门镖局/S01E01.mp4"
+//! # 写入媒体配置的 [[episodes]]
+//! cargo run -- detect "龙门镖局/S01E01.mp4" -n longmen -s 1 -e 1 --write
+//! # 通过音频指纹匹配一季所有剧集的片头片尾并写入
+//! cargo run -- detect "龙门镖局/龙门镖局1" -n longmen -s 1 --fingerprint --write
+//! ```
+use std::{fs, path::PathBuf};
+
+use anyhow::{anyhow, Result};
+use bili_video::{format_timestamp, Boundary, Detector, SeasonMatcher, Segment};
+use clap::{command, Parser};
+use media::{write_exclude_segments, MediaSettings};
+use regex::Regex;
+
+/// `detect` 命令的参数
+#[derive(Parser, Debug, Clone)]
+#[command(about, long_about = None)]
+pub struct DetectArgs {
+    path: String,
+
+    // 媒体名称
+    #[arg(short, long, help = "英文名，写入时使用")]
+    pub name: Option<String>,
+
+    // 季数
+    #[arg(short, long, help = "季数", default_value = "1")]
+    pub season: u16,
+
+    // 集数
+    #[arg(short, long, help = "集数，写入时使用")]
+    pub episode: Option<u16>,
+
+    // 是否写入配置
+    #[arg(short, long, help = "写入媒体配置的 exclude_segments")]
+    pub write: bool,
+
+    // 最低置信度
+    #[arg(long, help = "最低置信度，低于该值的候选不使用", default_value_t = 0.5)]
+    pub min_confidence: f64,
+
+    // 片头查找范围
+    #[arg(long, help = "在开头多少秒内查找片头")]
+    pub intro_window: Option<f64>,
+
+    // 片尾查找范围
+    #[arg(long, help = "在结尾多少秒内查找片尾")]
+    pub outro_window: Option<f64>,
+
+    // 是否使用音频指纹
+    #[arg(long, help = "path 为一季的目录，通过音频指纹匹配各集重复的片头片尾")]
+    pub fingerprint: bool,
+
+    // 片头片尾最短时长
+    #[arg(long, help = "指纹匹配时片头片尾的最短时长（秒）")]
+    pub min_duration: Option<f64>,
+}
+
+/// `detect` 命令入口
+pub fn detect(args: DetectArgs) -> Result<()> {
+    if args.fingerprint {
+        return detect_season(args);
+    }
+
+    let mut detector = Detector::default();
+    if let Some(window) = args.intro_window {
+        detector.intro_window = window;
+    }
+    if let Some(window) = args.outro_window {
+        detector.outro_window = window;
+    }
+
+    let detection = detector.detect(&args.path)?;
+    print_boundary("片头结束", detection.intro_end);
+    print_boundary("片尾开始", detection.outro_start);
+
+    let segments = detection.exclude_segments(args.min_confidence);
+    if segments.is_empty() {
+        println!("没有置信度不低于 {} 的片头片尾", args.min_confidence);
+        return Ok(());
+    }
+    print_segments(&segments);
+
+    if args.write {
+        let name = args.name.ok_or(anyhow!("写入时必须指定 --name"))?;
+        let episode = args.episode.ok_or(anyhow!("写入时必须指定 --episode"))?;
+        let path = MediaSettings::path(&name);
+        write_exclude_segments(&path, args.season, episode, &segments)?;
+        println!("已写入 {:?} S{:02}E{:02}", path, args.season, episode);
+    }
+    Ok(())
+}
+
+fn print_boundary(label: &str, boundary: Option<Boundary>) {
+    match boundary {
+        Some(b) => println!("{}: {}（置信度 {:.2}）", label, format_timestamp(b.time), b.confidence),
+        None => println!("{}: 未检测到", label),
+    }
+}
+
+/// 匹配目录中所有剧集的片头片尾，文件名需要包含 `SxxEyy`
+fn detect_season(args: DetectArgs) -> Result<()> {
+    let re = Regex::new(r"(?i)S(\d+)E(\d+)").unwrap();
+    let mut episodes: Vec<(PathBuf, u16, u16)> = Vec::new();
+    for entry in fs::read_dir(&args.path)? {
+        let path = entry?.path();
+        if path.extension().map_or(true, |x| x != "mp4") {
+            continue;
+        }
+        let name = path.file_name().unwrap().to_string_lossy().to_string();
+        match re.captures(&name) {
+            Some(caps) => episodes.push((path, caps[1].parse()?, caps[2].parse()?)),
+            None => println!("{:?} 文件名没有 SxxEyy，跳过", path),
+        }
+    }
+    episodes.sort_by_key(|x| (x.1, x.2));
+
+    let mut matcher = SeasonMatcher::default();
+    if let Some(window) = args.intro_window {
+        matcher.intro_window = window;
+    }
+    if let Some(window) = args.outro_window {
+        matcher.outro_window = window;
+    }
+    if let Some(duration) = args.min_duration {
+        matcher.min_duration = duration;
+    }
+    let paths: Vec<&PathBuf> = episodes.iter().map(|x| &x.0).collect();
+    println!("计算 {} 集的指纹", paths.len());
+    let matches = matcher.run(&paths)?;
+
+    let media_path = match (&args.name, args.write) {
+        (Some(name), true) => Some(MediaSettings::path(name)),
+        (None, true) => return Err(anyhow!("写入时必须指定 --name")),
+        _ => None,
+    };
+    for (m, (_, season, episode)) in matches.iter().zip(&episodes) {
+        println!("S{:02}E{:02} {:?}", season, episode, m.path);
+        let segments = m.exclude_segments();
+        if segments.is_empty() {
+            println!("没有匹配到片头片尾");
+            continue;
+        }
+        print_segments(&segments);
+        if let Some(path) = &media_path {
+            write_exclude_segments(path, *season, *episode, &segments)?;
+        }
+    }
+    if let Some(path) = media_path {
+        println!("已写入 {:?}", path);
+    }
+    Ok(())
+}
+
+fn print_segments(segments: &[Segment]) {
+    let pairs: Vec<String> = segments
+        .iter()
+        .map(|x| format!("[\"{}\", \"{}\"]", format_timestamp(x.start), format_timestamp(x.end)))
+        .collect();
+    println!("exclude_segments = [{}]", pairs.join(", "));
+}
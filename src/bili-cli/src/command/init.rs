@@ -9,12 +9,15 @@ use clap::{command, Parser};
 pub struct InitArgs {
     name: String,
 
+    // 是否标准化片段库的响度
+    #[arg(long, help = "标准化响度并替换片段库中的原文件")]
+    normalize: bool,
 }
 
 /// `init` 命令入口
 pub fn init(args: InitArgs) -> Result<()> {
     match args.name.as_str() {
-        "part" => init_part()?,
+        "part" => init_part(args.normalize)?,
         _ => eprintln!("not match init {}", &args.name),
     }
     Ok(())
@@ -0,0 +1,88 @@
+//! 检查媒体配置
+//!
+//! ```bash
+//! # 检查 Settings::media() 下所有媒体配置
+//! cargo run -- lint
+//! cargo run -- lint longmen
+//! cargo run -- lint -f src/bili-media/examples/media.toml
+//! ```
+use std::{fs, path::{Path, PathBuf}};
+
+use anyhow::{anyhow, Result};
+use clap::{command, Parser};
+use media::{references, MediaSettings};
+use settings::Settings;
+
+/// `lint` 命令的参数
+#[derive(Parser, Debug, Clone)]
+#[command(about, long_about = None)]
+pub struct LintArgs {
+    // 媒体名称
+    #[arg(help = "英文名，不指定时检查所有媒体配置")]
+    pub name: Option<String>,
+
+    // 配置文件
+    #[arg(short, long, help = "直接指定媒体配置文件", conflicts_with = "name")]
+    pub file: Option<PathBuf>,
+}
+
+/// `lint` 命令入口
+pub fn lint(args: LintArgs) -> Result<()> {
+    let paths = match (&args.file, &args.name) {
+        (Some(file), _) => vec![file.clone()],
+        (None, Some(name)) => vec![MediaSettings::path(name)],
+        (None, None) => {
+            let mut paths: Vec<PathBuf> = fs::read_dir(Settings::media())?
+                .filter_map(|x| x.ok().map(|x| x.path()))
+                .filter(|x| x.extension().is_some_and(|x| x == "toml"))
+                .collect();
+            paths.sort();
+            // 被 extends 或 include 引用的模板不是完整的媒体配置，随引用它的配置一起检查
+            let templates: Vec<PathBuf> = paths
+                .iter()
+                .filter_map(|x| references(x).ok())
+                .flatten()
+                .filter_map(|x| x.canonicalize().ok())
+                .collect();
+            paths.retain(|x| x.canonicalize().map_or(true, |x| !templates.contains(&x)));
+            paths
+        }
+    };
+
+    let mut errors = 0;
+    for path in &paths {
+        errors += lint_path(path);
+    }
+    if errors > 0 {
+        return Err(anyhow!("{} 个媒体配置中有 {} 个错误", paths.len(), errors));
+    }
+    println!("{} 个媒体配置没有发现错误", paths.len());
+    Ok(())
+}
+
+/// 检查单个媒体配置，返回错误数量
+fn lint_path(path: &Path) -> usize {
+    let media = match MediaSettings::read(path) {
+        Ok(media) => media,
+        Err(e) => {
+            println!("{:?}\n  错误: {}", path, e);
+            return 1;
+        }
+    };
+
+    let problems = media.lint();
+    if problems.is_empty() {
+        return 0;
+    }
+    println!("{:?}", path);
+    let mut last = None;
+    for problem in &problems {
+        if last != Some(&problem.place) {
+            println!("  {}", problem.place);
+            last = Some(&problem.place);
+        }
+        let level = if problem.fixable { "提示" } else { "错误" };
+        println!("    {}: {}", level, problem.message);
+    }
+    problems.iter().filter(|x| !x.fixable).count()
+}
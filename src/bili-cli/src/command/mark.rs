@@ -1,15 +1,18 @@
-use std::{fs, path::PathBuf};
+use std::{fs, path::{Path, PathBuf}};
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 
-use bili_video::{concat, to_ts, transcode_1080, Remover};
+use bili_video::{
+    concat, executor, to_ts, transcode, transcode_with_plan, CutMode, Remover, Segment, TranscodeProfile,
+};
 use clap::{command, Parser};
 use lazytool::path::must_get_filename;
-use media::{get_rand_part_path, MarkSettings, MediaSettings};
-use settings::Settings;
+use media::{get_rand_part_path, Edl, EdlEntry, MarkSettings, MediaSettings};
 
 use crate::create_cache_dir;
 
+use super::remove::warn_out_of_range;
+
 /// `mark` 命令的参数
 #[derive(Parser, Debug, Clone)]
 #[command(version, about, long_about = None)]
@@ -24,13 +27,42 @@ pub struct MarkArgs {
     // 是否使用快速分离
     #[arg(short('q'), long, help="是否快速分离")]
     pub with_quick: bool,
+
+    // 快速分离时允许的关键帧偏差
+    #[arg(long, help="快速分离时允许的关键帧偏差（秒），超过时重新编码")]
+    pub tolerance: Option<f64>,
+
+    // 是否使用智能分离
+    #[arg(long, help="智能分离，只重新编码两端不完整的 GOP", conflicts_with = "with_quick")]
+    pub smart: bool,
+
+    // 从 EDL 导入片段
+    #[arg(long, help="按 EDL 中的片段拼接，支持 json、csv、edl，没有来源的片段使用 path")]
+    pub edl: Option<PathBuf>,
+
+    // 导出 EDL
+    #[arg(long, help="把制作配置导出为 EDL，不制作视频")]
+    pub export_edl: Option<PathBuf>,
 }
 
 /// `mark` 命令入口
 pub fn mark(args: MarkArgs) -> Result<()> {
     // let settings = Settings::new()?;
     let media = MediaSettings::new(&args.name)?;
-    let m = media.get_mark(&args.id).expect("mark not found");
+    let mut m = media.get_mark(&args.id).expect("mark not found");
+    if let Some(path) = &args.edl {
+        let edl = Edl::from_path(path)?;
+        let source = m.path.as_ref().map(|x| x.to_string_lossy().to_string());
+        m.parts = Some(edl.parts(source.as_deref())?);
+        m.path = None;
+    }
+    if let Some(path) = &args.export_edl {
+        return export_edl(&args, &media, &m, path);
+    }
+    let profile = match m.profile() {
+        Some(name) => Some(media.get_profile(&name).ok_or(anyhow!("profile {} not found", name))?),
+        None => None,
+    };
 
     // 设置目标地址
     let title = if args.title.is_empty() { &m.title } else { &args.title };
@@ -38,44 +70,25 @@ pub fn mark(args: MarkArgs) -> Result<()> {
     let target_path = cache_dir.join(format!("{}.mp4", title));
 
     // 判断制作类型
-    if m.path.is_some() {
-        mark_path(args, &m, target_path)?;
+    let result = if m.path.is_some() {
+        mark_path(args, &m, profile.as_ref(), target_path)
+    } else if m.parts.is_some() {
+        mark_parts(args, &media, &m, profile.as_ref(), &cache_dir, target_path)
+    } else {
+        Err(anyhow!("mark {} 需要设置 path 或 parts", m.id))
+    };
+    // 失败或取消时删除未完成的缓存目录
+    if let Err(e) = result {
+        let _ = fs::remove_dir_all(&cache_dir);
+        return Err(e);
     }
-
-    // let mut paths: Vec<PathBuf> = Vec::new();
-    // if let Some(parts) = m.parts {
-        // for pid in parts {
-            // let path = settings.part.get_path(&args.name, &pid);
-            // if path.exists() {
-                // paths.push(path);
-            // } else {
-                // let path = PathBuf::from(&pid);
-                // if path.exists() {
-                    // paths.push(path);
-                // }
-            // }
-        // }
-    // }
-
-    // if let Some(suffix_parts) = m.suffix_parts {
-        // for name in suffix_parts {
-            // let path = get_rand_part_path(vec![name.clone()])?;
-            // if path.exists() {
-                // paths.push(path);
-            // }
-        // }
-    // }
-
-
-    // concat(&paths, &target_path)?;
-
-    // println!("{paths:#?}");
     Ok(())
 }
 
 pub fn mark_path(
     args: MarkArgs,
     mark_config: &MarkSettings,
+    profile: Option<&TranscodeProfile>,
     target_path: PathBuf,
 ) -> Result<()> {
 
@@ -83,17 +96,27 @@ pub fn mark_path(
     let mut cache_path = target_path.with_extension("cache.mp4");
     fs::copy(&path, &cache_path)?;
 
-    if let Some(exclude) = &mark_config.exclude_segments {
+    // 设置了 include_segments 时只保留这些片段，否则删除 exclude_segments
+    let segments = match mark_config.keep_segments() {
+        Some(keep) => Some((keep, true)),
+        None => mark_config.exclude_segments.clone().map(|x| (x, false)),
+    };
+    if let Some((segments, keep_only)) = segments {
         let remove_path = cache_path.with_extension("remove.mp4");
-        let mut r = Remover::new(&cache_path, exclude.to_vec());
-        r.with_quick(args.with_quick).output(&remove_path);
-        fs::remove_file(&cache_path);
+        let mut r = Remover::new(&cache_path, segments);
+        r.with_quick(args.with_quick).set_tolerance(args.tolerance).set_keep_only(keep_only);
+        warn_out_of_range(&r, &mark_config.id)?;
+        if args.smart {
+            r.set_mode(CutMode::Smart);
+        }
+        r.output(&remove_path)?;
+        fs::remove_file(&cache_path)?;
         cache_path = remove_path;
     }
 
-    if mark_config.trans_1080p() {
+    if let Some(profile) = profile {
         let trans_path = cache_path.with_extension("trans.mp4");
-        transcode_1080(&cache_path, &trans_path)?;
+        transcode(&cache_path, &trans_path, profile)?;
         fs::remove_file(&cache_path)?;
         cache_path = trans_path
     }
@@ -103,6 +126,105 @@ pub fn mark_path(
     Ok(())
 }
 
+/// 按 `parts` 拼接视频，每个片段先截取，再转码成统一格式，后缀视频同样转码
+///
+/// 没有指定转码配置时使用默认的 `1080p`，和片段库的格式一致
+pub fn mark_parts(
+    args: MarkArgs,
+    media: &MediaSettings,
+    mark_config: &MarkSettings,
+    profile: Option<&TranscodeProfile>,
+    cache_dir: &Path,
+    target_path: PathBuf,
+) -> Result<()> {
+    let default_profile = TranscodeProfile::default();
+    let profile = profile.unwrap_or(&default_profile);
+    let parts = mark_config.parts.clone().unwrap_or_default();
+
+    let mut paths: Vec<PathBuf> = Vec::new();
+    for (i, part) in parts.iter().enumerate() {
+        let mut path = media.get_part_path(&part.id)?;
+        println!("{} => {:?}", part.id, path);
+
+        if let Some(segment) = part.segment {
+            let cut_path = cache_dir.join(format!("{:02}.cut.mp4", i));
+            let mut r = Remover::new(&path, vec![segment]);
+            r.with_quick(args.with_quick).set_tolerance(args.tolerance).set_keep_only(true);
+            warn_out_of_range(&r, &part.id)?;
+            if args.smart {
+                r.set_mode(CutMode::Smart);
+            }
+            path = r.output(&cut_path)?;
+        }
+
+        paths.push(conform_part(&path, &part.id, profile, &cache_dir.join(format!("{:02}", i)))?);
+    }
+
+    // 后缀视频也要和片段格式一致才能直接拼接
+    if mark_config.with_suffix() {
+        for (i, path) in suffix_paths(mark_config)?.iter().enumerate() {
+            let name = must_get_filename(path);
+            paths.push(conform_part(path, &name, profile, &cache_dir.join(format!("suffix{:02}", i)))?);
+        }
+    }
+    concat(&paths, &target_path)?;
+    Ok(())
+}
+
+/// 按转码配置统一片段格式并转为 ts，`cache_prefix` 是缓存文件的路径前缀
+fn conform_part(path: &Path, name: &str, profile: &TranscodeProfile, cache_prefix: &Path) -> Result<PathBuf> {
+    let mut path = path.to_path_buf();
+    let plan = profile.plan(&executor().probe(&path)?);
+    if !plan.is_copy() {
+        println!("{}: {}", name, plan);
+        let trans_path = cache_prefix.with_extension("trans.mp4");
+        transcode_with_plan(&path, &trans_path, profile, &plan)?;
+        path = trans_path;
+    }
+
+    // 片段库中的 ts 直接拼接
+    if !must_get_filename(&path).ends_with("ts") {
+        path = to_ts(&path, Some(&cache_prefix.with_extension("ts")))?;
+    }
+    Ok(path)
+}
+
+/// 把制作配置导出为 EDL，方便在剪辑软件中调整后再用 `--edl` 导入
+fn export_edl(args: &MarkArgs, media: &MediaSettings, mark_config: &MarkSettings, path: &Path) -> Result<()> {
+    let mut entries = Vec::new();
+    if let Some(parts) = &mark_config.parts {
+        for part in parts {
+            let source = media.get_part_path(&part.id)?;
+            let segment = match part.segment {
+                Some(segment) => segment,
+                None => Segment::new(0.0, executor().probe(&source)?.duration),
+            };
+            let mut entry = EdlEntry::new(Some(source.to_string_lossy().to_string()), segment);
+            entry.label = Some(part.id.clone());
+            entries.push(entry);
+        }
+    } else if let Some(source) = &mark_config.path {
+        // 和制作时一样计算保留的片段
+        let (segments, keep_only) = match mark_config.keep_segments() {
+            Some(keep) => (keep, true),
+            None => (mark_config.exclude_segments.clone().unwrap_or_default(), false),
+        };
+        let mut r = Remover::new(source, segments);
+        r.with_quick(args.with_quick).set_tolerance(args.tolerance).set_keep_only(keep_only);
+        for cut in r.plan()? {
+            entries.push(EdlEntry::new(Some(source.to_string_lossy().to_string()), cut.requested));
+        }
+    } else {
+        return Err(anyhow!("mark {} 需要设置 path 或 parts", mark_config.id));
+    }
+
+    let mut edl = Edl::new(entries);
+    edl.title = Some(mark_config.title.clone());
+    edl.write(path)?;
+    println!("已导出 {:?}", path);
+    Ok(())
+}
+
 /// 合并后缀视频
 pub fn concat_suffix(
     mark_config: &MarkSettings,
@@ -120,15 +242,22 @@ pub fn concat_suffix(
         } else {
             paths.push(source_path.to_path_buf());
         }
-        if let Some(suffix_parts) = &mark_config.suffix_parts {
-            for name in suffix_parts {
-                let path = get_rand_part_path(vec![name.clone()])?;
-                if path.exists() {
-                    paths.push(path);
-                }
-            }
-        }
+        paths.extend(suffix_paths(mark_config)?);
         concat(&paths, &target_path)?;
     }
     Ok(())
 }
+
+/// 随机选取的后缀视频
+fn suffix_paths(mark_config: &MarkSettings) -> Result<Vec<PathBuf>> {
+    let mut paths: Vec<PathBuf> = Vec::new();
+    if let Some(suffix_parts) = &mark_config.suffix_parts {
+        for name in suffix_parts {
+            let path = get_rand_part_path(vec![name.clone()])?;
+            if path.exists() {
+                paths.push(path);
+            }
+        }
+    }
+    Ok(paths)
+}
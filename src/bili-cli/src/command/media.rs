@@ -0,0 +1,52 @@
+//! 查看媒体配置
+//!
+//! ```bash
+//! # 打印配置文件原文
+//! cargo run -- media show longmen
+//! # 打印合并 extends 和 include 后生效的配置
+//! cargo run -- media show longmen --resolved
+//! ```
+use std::fs;
+
+use anyhow::Result;
+use clap::{command, Parser, Subcommand};
+use media::{resolve, MediaSettings};
+
+/// `media` 命令的参数
+#[derive(Parser, Debug, Clone)]
+#[command(about, long_about = None)]
+pub struct MediaArgs {
+    #[command(subcommand)]
+    pub command: MediaCommand,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum MediaCommand {
+    /// 打印媒体配置
+    Show {
+        // 媒体名称
+        #[arg(help = "英文名")]
+        name: String,
+
+        // 是否合并引用的配置
+        #[arg(long, help = "打印合并 extends 和 include 后生效的配置")]
+        resolved: bool,
+    },
+}
+
+/// `media` 命令入口
+pub fn media(args: MediaArgs) -> Result<()> {
+    match args.command {
+        MediaCommand::Show { name, resolved } => {
+            let path = MediaSettings::path(&name);
+            if resolved {
+                // 检查合并后的配置是否有效
+                MediaSettings::resolve(&path)?;
+                println!("{}", toml::to_string_pretty(&resolve(&path)?)?);
+            } else {
+                println!("{}", fs::read_to_string(&path)?);
+            }
+        }
+    }
+    Ok(())
+}
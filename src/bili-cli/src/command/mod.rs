@@ -5,6 +5,10 @@ mod upload;
 mod upload_file;
 mod mark;
 mod remove;
+mod detect;
+mod lint;
+mod schema;
+mod media;
 pub mod model;
 
 pub use trans::{trans, TransArgs};
@@ -14,3 +18,8 @@ pub use upload::{upload, UploadArgs};
 pub use upload_file::{upload_file, UploadFileArgs};
 pub use mark::{mark, MarkArgs};
 pub use remove::{remove, RemoveArgs};
+pub use detect::{detect, DetectArgs};
+pub use lint::{lint, LintArgs};
+pub use schema::{schema, SchemaArgs};
+// 和 media 库同名，需要指明是当前模块
+pub use self::media::{media, MediaArgs};
@@ -4,7 +4,7 @@ use anyhow::{anyhow, Result};
 
 use clap::{command, Parser};
 use lazytool::path::must_to_string;
-use media::MediaSettings;
+use media::{MediaId, MediaKind, MediaSettings};
 use settings::Settings;
 
 use crate::create_cache_dir;
@@ -14,8 +14,8 @@ use crate::create_cache_dir;
 #[derive(Parser, Debug, Clone)]
 #[command(about, long_about = None)]
 pub struct EpisodeArgs {
-    #[arg(long("type"), default_value = "电视剧", help="类型")]
-    pub type_: String,
+    #[arg(long("type"), help="类型：电视剧、电影、综艺、纪录片，默认读取媒体配置")]
+    pub type_: Option<MediaKind>,
 
     // 短命
     #[arg(short, long, help="英文名", default_value_t)]
@@ -30,17 +30,17 @@ pub struct EpisodeArgs {
     pub episode_title: String,
 
     // 季数
-    #[arg(short, long, help="季数", default_value = "1")]
+    #[arg(short, long, help="季数，电影和综艺为年份", default_value = "1")]
     pub season: u16,
 
     // 集数
-    #[arg(short, long, help="集数")]
+    #[arg(short, long, help="集数，电影为第几部分，综艺为播出日期如 621")]
     pub episode: u16,
 }
 
 impl EpisodeArgs {
 
-    pub fn new(type_: String, name: Option<String>, title: String, season: u16, episode: u16) -> Self {
+    pub fn new(type_: Option<MediaKind>, name: Option<String>, title: String, season: u16, episode: u16) -> Self {
         let mut n = "".to_string();
         if let Some(name_) = name {
             n = name_;
@@ -59,8 +59,9 @@ impl EpisodeArgs {
                 }
             }
         }
-        if self.season > 1000 {
-            self.type_ = "电影".to_string();
+        // 只使用配置中填写的类型，没有填写时仍按旧规则推断
+        if self.type_.is_none() {
+            self.type_ = media.kind;
         }
         self
     }
@@ -74,37 +75,37 @@ impl EpisodeArgs {
         }
     }
 
-    pub fn is_drama(&self) -> bool {
-        self.type_ == "电视剧"
+    /// 媒体类型，没有指定时按旧规则推断
+    pub fn kind(&self) -> MediaKind {
+        self.type_.or(MediaKind::guess(self.season)).unwrap_or_default()
     }
 
-    pub fn get_full_title(&self) -> String {
-        if self.is_drama() {
-            format!("{}S{:02}E{:02}", &self.title, self.season, self.episode)
-        } else {
-            format!("{}.{:04}.{:05}", &self.episode_title, self.season, self.episode)
-        }
+    /// 按媒体类型解释的季和集，季和集不是这个类型的有效编号时返回错误
+    pub fn media_id(&self) -> Result<MediaId> {
+        self.kind().check(self.season, self.episode)?;
+        Ok(MediaId::from_kind(self.type_, self.season, self.episode))
+    }
+
+    pub fn get_full_title(&self) -> Result<String> {
+        Ok(self.media_id()?.title(&self.title, &self.episode_title))
     }
 
     pub fn get_path(&self) -> Result<PathBuf> {
         let media = MediaSettings::new(&self.get_name().expect("failed get name"))?;
-        let path = media.media_dir()
-            .join(&self.type_)
-            .join(&media.title)
-            .join(format!("{}{}", &media.title, &self.season))
-            .join(format!("S{:02}E{:02}.mp4", &self.season, &self.episode));
-        Ok(path)
+        let mut ep = self.clone();
+        ep.fill_from_media(&media);
+        Ok(media.media_dir().join(ep.media_id()?.path(&media.title, &ep.episode_title)))
     }
 
     /// 创建临时目录
     pub fn create_cache_dir(&self) -> Result<PathBuf> {
-        let dir = create_cache_dir(self.get_full_title())?;
+        let dir = create_cache_dir(self.get_full_title()?)?;
         Ok(dir)
     }
 
     /// 获取分割视频的缓存目录
     pub fn get_cache_dir(&self) -> Result<PathBuf> {
-        let name = self.get_full_title();
+        let name = self.get_full_title()?;
         let mut names: Vec<String> = Vec::new();
         let cache_dir = Settings::cache();
         for entry in fs::read_dir(&cache_dir)? {
@@ -126,8 +127,10 @@ impl EpisodeArgs {
 
 #[cfg(test)]
 mod tests {
+    use std::path::PathBuf;
+
     use clap::Parser;
-    use media::MediaSettings;
+    use media::{MediaKind, MediaSettings};
 
     use super::EpisodeArgs;
 
@@ -142,7 +145,7 @@ mod tests {
         ep.fill_from_media(&media);
         assert_eq!(ep.title, "多媒体");
         assert_eq!(ep.episode_title, "标题");
-        assert_eq!(ep.get_full_title(), "多媒体S01E02");
+        assert_eq!(ep.get_full_title().unwrap(), "多媒体S01E02");
 
         let mut ep = EpisodeArgs::try_parse_from([
             "test",
@@ -152,6 +155,34 @@ mod tests {
         ]).unwrap();
         let media = MediaSettings::from_path("../bili-media/examples/media.toml").unwrap();
         ep.fill_from_media(&media);
-        assert_eq!(ep.get_full_title(), "疯狂的赛车.2009.01201");
+        assert_eq!(ep.get_full_title().unwrap(), "疯狂的赛车.2009.01201");
+
+        // 没有填写类型的电影保持旧的标题和路径
+        ep.episode = 1;
+        ep.episode_title = "疯狂的赛车".to_string();
+        assert_eq!(ep.get_full_title().unwrap(), "疯狂的赛车.2009.00001");
+        assert_eq!(
+            ep.media_id().unwrap().path(&ep.title, &ep.episode_title),
+            PathBuf::from("电影/多媒体/多媒体2009/S2009E01.mp4"),
+        );
+
+        // 填写了电影类型时集数是第几部分
+        ep.type_ = Some(MediaKind::Movie);
+        ep.episode = 120;
+        assert_eq!(ep.get_full_title().unwrap(), "疯狂的赛车.2009.Part120");
+
+        let mut ep = EpisodeArgs::try_parse_from([
+            "test",
+            "-n", "media",
+            "-s", "2024",
+            "-e", "621",
+            "--type", "variety",
+        ]).unwrap();
+        ep.fill_from_media(&media);
+        assert_eq!(ep.get_full_title().unwrap(), "多媒体.2024-06-21");
+
+        // 综艺的集数是播出日期
+        ep.episode = 1;
+        assert!(ep.get_full_title().is_err());
     }
 }
@@ -8,14 +8,15 @@
 use std::{
     fs,
     path::{Path, PathBuf},
+    sync::Arc,
 };
 
 use anyhow::{anyhow, Result};
 
-use bili_video::Remover;
+use bili_video::{parse_timestamp, CutMode, DryRunExecutor, Remover, Segment};
 use clap::{command, Parser};
 use lazytool::{path::must_get_filename, Episode};
-use media::MediaSettings;
+use media::{Edl, EdlEntry, MediaSettings};
 use settings::Settings;
 use tokio::time::error::Elapsed;
 
@@ -34,11 +35,11 @@ pub struct RemoveArgs {
     /// 指定时间对
     #[arg(
         long,
-        help = "指定时间对，格式为 x,y",
+        help = "指定时间对，格式为 x,y，时间可以是秒数或 hh:mm:ss.mmm",
         value_parser = parse_pair,
         action = clap::ArgAction::Append
     )]
-    pub pairs: Vec<(u64, u64)>,
+    pub pairs: Vec<Segment>,
 
     // 是否执行
     // #[arg(short, long, help = "是否执行")]
@@ -47,6 +48,30 @@ pub struct RemoveArgs {
     // 是否使用快速分离
     #[arg(short('q'), long, help="是否快速分离")]
     pub with_quick: bool,
+
+    // 快速分离时允许的关键帧偏差
+    #[arg(long, help="快速分离时允许的关键帧偏差（秒），超过时重新编码")]
+    pub tolerance: Option<f64>,
+
+    // 是否使用智能分离
+    #[arg(long, help="智能分离，只重新编码两端不完整的 GOP", conflicts_with = "with_quick")]
+    pub smart: bool,
+
+    // 只保留指定片段
+    #[arg(long, help="只保留指定的时间对，按顺序合并")]
+    pub keep: bool,
+
+    // 从 EDL 导入片段
+    #[arg(long, help="只保留 EDL 中的片段，支持 json、csv、edl", conflicts_with = "pairs")]
+    pub edl: Option<PathBuf>,
+
+    // 导出 EDL
+    #[arg(long, help="把保留的片段导出为 EDL，不执行")]
+    pub export_edl: Option<PathBuf>,
+
+    // 只打印执行计划
+    #[arg(long, help="只打印 ffmpeg 命令，不执行")]
+    pub dry_run: bool,
 }
 
 /// `trans` 命令入口
@@ -61,22 +86,53 @@ pub fn remove(args: RemoveArgs) -> anyhow::Result<()> {
         Path::new(&default_to)
     };
     println!("{:?}", to);
-    let mut r = Remover::new(from, args.pairs);
+    if args.dry_run {
+        bili_video::set_executor(Arc::new(DryRunExecutor::new()));
+    }
+    // EDL 中是最终保留的片段
+    let (pairs, keep) = match &args.edl {
+        Some(path) => (Edl::from_path(path)?.segments(), true),
+        None => (args.pairs.clone(), args.keep),
+    };
+    let mut r = Remover::new(from, pairs);
     if args.with_quick {
         r.with_quick(args.with_quick);
     }
+    if args.smart {
+        r.set_mode(CutMode::Smart);
+    }
+    r.set_tolerance(args.tolerance).set_keep_only(keep);
+    warn_out_of_range(&r, from)?;
+
+    if let Some(path) = &args.export_edl {
+        let entries = r.plan()?.into_iter().map(|x| EdlEntry::new(Some(from.clone()), x.requested)).collect();
+        Edl::new(entries).write(path)?;
+        println!("已导出 {:?}，修改后使用 --edl 导入", path);
+        return Ok(());
+    }
     r.output(to)?;
     Ok(())
 }
 
-fn parse_pair(s: &str) -> Result<(u64, u64), String> {
+/// 提示超出视频时长、截取时被丢弃的片段，`label` 是所属的视频或剧集
+pub(crate) fn warn_out_of_range(r: &Remover, label: &str) -> Result<()> {
+    for e in r.out_of_range()? {
+        println!("{} 忽略{}", label, e);
+    }
+    Ok(())
+}
+
+/// 解析时间对，时间可以是秒数或 `hh:mm:ss.mmm`
+fn parse_pair(s: &str) -> Result<Segment, String> {
     let parts: Vec<&str> = s.split(',').collect();
     if parts.len() != 2 {
         return Err("参数格式错误，应该为 x,y".into());
     }
 
-    let x = parts[0].parse::<u64>().map_err(|_| "x 不是有效的数字")?;
-    let y = parts[1].parse::<u64>().map_err(|_| "y 不是有效的数字")?;
+    let x = parse_timestamp(parts[0]).map_err(|_| "x 不是有效的时间")?;
+    let y = parse_timestamp(parts[1]).map_err(|_| "y 不是有效的时间")?;
 
-    Ok((x, y))
+    let segment = Segment::new(x, y);
+    segment.validate().map_err(|e| e.to_string())?;
+    Ok(segment)
 }
@@ -0,0 +1,53 @@
+//! 生成配置文件的 JSON Schema
+//!
+//! ```bash
+//! cargo run -- schema settings -o ~/.bilibili/bilibili.schema.json
+//! cargo run -- schema media -o ~/.bilibili/media.schema.json
+//! ```
+//!
+//! 在媒体配置第一行写 `#:schema ../media.schema.json`，taplo 和 VS Code 的 Even Better TOML 会按 Schema 补全和校验
+use std::{fs, path::PathBuf};
+
+use anyhow::Result;
+use clap::{command, Parser, ValueEnum};
+use media::MediaSettings;
+use schemars::schema_for;
+use settings::Settings;
+
+/// 生成哪个配置的 Schema
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum SchemaKind {
+    /// bilibili.toml
+    Settings,
+    /// 媒体配置
+    Media,
+}
+
+/// `schema` 命令的参数
+#[derive(Parser, Debug, Clone)]
+#[command(about, long_about = None)]
+pub struct SchemaArgs {
+    #[arg(value_enum)]
+    pub kind: SchemaKind,
+
+    // 输出文件
+    #[arg(short, long, help = "输出文件，不指定时打印到标准输出")]
+    pub output: Option<PathBuf>,
+}
+
+/// `schema` 命令入口
+pub fn schema(args: SchemaArgs) -> Result<()> {
+    let schema = match args.kind {
+        SchemaKind::Settings => schema_for!(Settings),
+        SchemaKind::Media => schema_for!(MediaSettings),
+    };
+    let text = serde_json::to_string_pretty(&schema)?;
+    match &args.output {
+        Some(path) => {
+            fs::write(path, text)?;
+            println!("已写入 {:?}", path);
+        }
+        None => println!("{}", text),
+    }
+    Ok(())
+}
@@ -1,5 +1,5 @@
-use std::{fs, path::PathBuf};
-use bili_video::{Remover, Spliter};
+use std::{fs, path::{Path, PathBuf}};
+use bili_video::{parse_timestamp, BoundaryMode, CutMode, Remover, SplitBy, Spliter, TranscodeProfile};
 use lazytool::path::must_get_filename;
 use media::{get_rand_part_path, MediaSettings, SpliterSettings};
 
@@ -8,7 +8,7 @@ use anyhow::{Result, anyhow};
 use clap::{command, Parser};
 use settings::Settings;
 
-use super::model::EpisodeArgs;
+use super::{model::EpisodeArgs, remove::warn_out_of_range};
 
 /// `split` 命令的参数
 #[derive(Parser, Debug, Clone)]
@@ -26,13 +26,65 @@ pub struct SplitArgs {
     #[arg(short, long, help="分割数量", default_value_t)]
     pub count: usize,
 
+    // 每一部分的最大时长
+    #[arg(long, help="每一部分的最大时长，可以是秒数或 hh:mm:ss，数量由视频时长决定", value_parser = parse_time)]
+    pub max_duration: Option<f64>,
+
+    // 指定分割时间点
+    #[arg(
+        long,
+        help="在指定时间点分割，可以是秒数或 hh:mm:ss，可以多次指定",
+        value_parser = parse_time,
+        action = clap::ArgAction::Append
+    )]
+    pub split_at: Vec<f64>,
+
     // 是否使用快速分离
     #[arg(short('q'), long, help="是否快速分离")]
     pub with_quick: bool,
 
+    // 快速分离时允许的关键帧偏差
+    #[arg(long, help="快速分离时允许的关键帧偏差（秒），超过时重新编码")]
+    pub tolerance: Option<f64>,
+
+    // 是否使用智能分离
+    #[arg(long, help="智能分离，只重新编码两端不完整的 GOP", conflicts_with = "with_quick")]
+    pub smart: bool,
+
     // 是否使用缓存
     #[arg(short('C'), long, help="是否使用缓存")]
     pub with_cache: bool,
+
+    // 每一部分的最大大小
+    #[arg(long, help="每一部分的最大大小（MB）")]
+    pub max_size: Option<f64>,
+
+    // 两遍编码到最大大小
+    #[arg(long, help="按转码配置两遍编码，使每一部分不超过最大大小")]
+    pub size_profile: Option<String>,
+
+    // 分割点对齐方式
+    #[arg(long, help="分割点对齐方式：fixed 等分，scene 对齐场景切换，silence 对齐静音")]
+    pub boundary: Option<BoundaryMode>,
+
+    // 查找范围
+    #[arg(long, help="在理想分割点前后多少秒内查找场景切换或静音")]
+    pub boundary_window: Option<f64>,
+}
+
+impl SplitArgs {
+    /// 分割方式，优先使用指定时间点，其次最大时长，最后数量
+    pub fn split_by(&self) -> Option<SplitBy> {
+        if !self.split_at.is_empty() {
+            Some(SplitBy::Timestamps(self.split_at.clone()))
+        } else if let Some(duration) = self.max_duration {
+            Some(SplitBy::MaxDuration(duration))
+        } else if self.count > 0 {
+            Some(SplitBy::Parts(self.count))
+        } else {
+            None
+        }
+    }
 }
 
 /// `split` 命令入口
@@ -51,14 +103,34 @@ pub fn split(args: SplitArgs) -> anyhow::Result<()> {
     let spliter = media.get_spliter(ep.season, ep.episode).unwrap();
     println!("{spliter:#?}");
 
-    // 封装分割数量
-    if args.count == 0 && spliter.count.is_some(){
-        args.count = spliter.count.unwrap();
+    // 命令行没有指定分割方式时使用配置
+    if args.split_by().is_none() {
+        match spliter.split_by() {
+            Some(SplitBy::Parts(count)) => args.count = count,
+            Some(SplitBy::MaxDuration(duration)) => args.max_duration = Some(duration),
+            Some(SplitBy::Timestamps(timestamps)) => args.split_at = timestamps,
+            None => {}
+        }
     }
-    if args.count == 0 {
+    if args.split_by().is_none() {
         return Err(anyhow!("count is 0"));
     }
 
+    // 封装最大大小
+    if args.max_size.is_none() {
+        args.max_size = spliter.max_size;
+    }
+    if args.boundary.is_none() {
+        args.boundary = spliter.boundary;
+    }
+    if args.boundary_window.is_none() {
+        args.boundary_window = spliter.boundary_window;
+    }
+    let size_profile = match args.size_profile.clone().or(spliter.size_profile.clone()) {
+        Some(name) => Some(media.get_profile(&name).ok_or(anyhow!("profile {} not found", name))?),
+        None => None,
+    };
+
     let suffix_parts = spliter.suffix_parts.clone().expect("toml not found suffix_parts");
 
     // 转码时已经移除片头片段，分割时不再使用视频的片头
@@ -70,12 +142,27 @@ pub fn split(args: SplitArgs) -> anyhow::Result<()> {
         // }
     // }
 
-    let split_ts = if args.with_cache { get_cache_ts_list(&args)? } else { split_and_to_ts(&args, &spliter)? };
+    let split_ts = if args.with_cache { get_cache_ts_list(&args)? } else { split_and_to_ts(&args, &spliter, size_profile)? };
     println!("{split_ts:#?}");
+    let result = concat_suffix_and_screenshot(&split_ts, &suffix_parts, &spliter);
+    // 失败或取消时删除未完成的缓存目录，避免被上传
+    if result.is_err() {
+        if let Some(dir) = split_ts.first().and_then(|x| x.parent()) {
+            let _ = fs::remove_dir_all(dir);
+        }
+    }
+    result
+}
+
+fn concat_suffix_and_screenshot(
+    split_ts: &[PathBuf],
+    suffix_parts: &[String],
+    spliter: &SpliterSettings,
+) -> Result<()> {
     for ts in split_ts {
         // 拼接后缀
         let mut need_concat_ts = vec![ts.clone()];
-        for part in &suffix_parts {
+        for part in suffix_parts {
             need_concat_ts.push(get_rand_part_path(vec![part.to_string()])?);
         }
 
@@ -94,12 +181,33 @@ pub fn split(args: SplitArgs) -> anyhow::Result<()> {
 pub fn split_and_to_ts(
     args: &SplitArgs,
     spliter: &SpliterSettings,
+    size_profile: Option<TranscodeProfile>,
+) -> Result<Vec<PathBuf>> {
+    let cache = args.ep.create_cache_dir()?;
+    let ts_cache_dir = get_cache_ts_dir(args)?;
+    let has_ts_cache = ts_cache_dir.exists();
+
+    let result = split_to_cache(args, spliter, size_profile, &cache);
+    // 失败或取消时删除未完成的缓存
+    if result.is_err() {
+        let _ = fs::remove_dir_all(&cache);
+        if !has_ts_cache {
+            let _ = fs::remove_dir_all(&ts_cache_dir);
+        }
+    }
+    result
+}
+
+fn split_to_cache(
+    args: &SplitArgs,
+    spliter: &SpliterSettings,
+    size_profile: Option<TranscodeProfile>,
+    cache: &Path,
 ) -> Result<Vec<PathBuf>> {
     let ep = args.ep.clone();
-    let cache = ep.create_cache_dir()?;
-    let mut target_name = ep.get_full_title();
+    let mut target_name = ep.get_full_title()?;
     if !ep.episode_title.is_empty() {
-        target_name = format!("{}-{}", &ep.episode_title, ep.get_full_title())
+        target_name = format!("{}-{}", &ep.episode_title, target_name)
     }
     // 使用别名
     if !args.alias.is_empty() {
@@ -116,7 +224,12 @@ pub fn split_and_to_ts(
             println!("remove_parts {:?}", &remove_parts);
             let remove_part_path = cache.join(&target_name).with_extension("remove.mp4");
             let mut r = Remover::new(&cache_path, remove_parts.to_vec());
-            r.with_quick(args.with_quick).output(&remove_part_path)?;
+            r.with_quick(args.with_quick).set_tolerance(args.tolerance);
+            warn_out_of_range(&r, &target_name)?;
+            if args.smart {
+                r.set_mode(CutMode::Smart);
+            }
+            r.output(&remove_part_path)?;
             fs::remove_file(&cache_path)?;
             cache_path = remove_part_path;
         }
@@ -125,10 +238,21 @@ pub fn split_and_to_ts(
     let split_target = cache.join(target_name);
     // 分割
     let mut s = Spliter::new(&cache_path);
-    let split_paths = s
-        .set_parts(args.count)
+    s.set_split_by(args.split_by().ok_or(anyhow!("count is 0"))?)
         .with_quick(args.with_quick)
-        .output(split_target)?;
+        .set_tolerance(args.tolerance)
+        .set_max_size(args.max_size)
+        .set_size_profile(size_profile);
+    if args.smart {
+        s.set_mode(CutMode::Smart);
+    }
+    if let Some(boundary) = args.boundary {
+        s.set_boundary(boundary);
+    }
+    if let Some(window) = args.boundary_window {
+        s.set_boundary_window(window);
+    }
+    let split_paths = s.output(split_target)?;
 
     let ts_cache_dir = create_cache_ts_dir(args)?;
 
@@ -159,11 +283,23 @@ fn get_cache_ts_dir(args: &SplitArgs) -> Result<PathBuf> {
     let dir = root
         .join("split")
         .join(&args.ep.name)
-        .join(format!("{}-{}", args.ep.get_full_title(), args.count));
+        .join(format!("{}-{}", args.ep.get_full_title()?, split_key(args)));
     println!("cache ts dir: {dir:?}");
     Ok(dir)
 }
 
+/// 缓存目录中区分分割方式的部分，按数量分割时为数量
+fn split_key(args: &SplitArgs) -> String {
+    match args.split_by() {
+        Some(SplitBy::Timestamps(timestamps)) => {
+            let points: Vec<String> = timestamps.iter().map(|x| x.to_string()).collect();
+            format!("at{}", points.join("_"))
+        }
+        Some(SplitBy::MaxDuration(duration)) => format!("max{}", duration),
+        _ => args.count.to_string(),
+    }
+}
+
 fn get_cache_ts_list(args: &SplitArgs) -> Result<Vec<PathBuf>> {
     let cache_dir = args.ep.create_cache_dir()?;
     let cache_ts_dir = get_cache_ts_dir(args)?;
@@ -183,3 +319,8 @@ fn get_cache_ts_list(args: &SplitArgs) -> Result<Vec<PathBuf>> {
     }
     Ok(results)
 }
+
+/// 解析时间，可以是秒数或 `hh:mm:ss.mmm`
+fn parse_time(s: &str) -> Result<f64, String> {
+    parse_timestamp(s).map_err(|e| e.to_string())
+}
@@ -15,10 +15,10 @@ use anyhow::{anyhow, Result};
 use bili_video::Remover;
 use clap::{command, Parser};
 use lazytool::{path::must_get_filename, Episode};
-use media::MediaSettings;
+use media::{MediaKind, MediaSettings};
 use settings::Settings;
 
-use crate::command::model::EpisodeArgs;
+use crate::command::{model::EpisodeArgs, remove::warn_out_of_range};
 
 /// `trans` 命令的参数
 #[derive(Parser, Debug, Clone)]
@@ -27,12 +27,12 @@ pub struct TransArgs {
     path: String,
 
     // 动作
-    #[arg(short, long, default_value = "1080p", help = "转码动作")]
+    #[arg(short, long, default_value = "1080p", help = "转码动作：mp3、mp4、m3u8 或转码配置名称")]
     pub action: String,
 
     // 视频类型
-    #[arg(long("type"), default_value = "电视剧", help = "类型")]
-    pub type_: String,
+    #[arg(long("type"), help = "类型：电视剧、电影、综艺、纪录片，默认读取媒体配置")]
+    pub type_: Option<MediaKind>,
 
     // 剧名
     #[arg(short, long, help = "剧名", default_value_t)]
@@ -61,6 +61,10 @@ pub struct TransArgs {
     // 是否保留片头片尾
     #[arg(short('r'), long, help = "是否保留片头")]
     pub is_reserve: bool,
+
+    // 目标大小
+    #[arg(long, help = "目标大小（MB），使用两遍编码")]
+    pub target_size: Option<f64>,
 }
 
 /// `trans` 命令入口
@@ -135,13 +139,13 @@ impl Trans for Mp4Trans {
     }
 }
 
+/// 按转码配置转码，如 `1080p`、`720p`、`4k-hevc`
 #[derive(Debug)]
-struct Mp41080Trans {}
+struct ProfileTrans {
+    profile: String,
+}
 
-impl Trans for Mp41080Trans {
-    // fn get_action(&self) -> String {
-        // "1080p".to_string()
-    // }
+impl Trans for ProfileTrans {
 
     fn trans(&self, args: &TransArgs) -> Result<()> {
         let ep = trans_to_episode(args)?;
@@ -152,6 +156,12 @@ impl Trans for Mp41080Trans {
         println!("{name}");
 
         let media = MediaSettings::new(&name)?;
+        let mut profile = media
+            .get_profile(&self.profile)
+            .ok_or(anyhow!("{} not match", &self.profile))?;
+        if args.target_size.is_some() {
+            profile.target_size = args.target_size;
+        }
 
         let to = ep.get_path()?;
         println!("转码目标地址: {to:?}");
@@ -164,12 +174,18 @@ impl Trans for Mp41080Trans {
         }
         let episode_settings = media.get_episode(ep.season, ep.episode);
         println!("{episode_settings:#?}");
+        println!("{profile:#?}");
+
+        // 符合转码配置的流直接复制
+        let source = bili_video::executor().probe(Path::new(&args.path))?;
+        let plan = profile.plan(&source);
+        println!("转码计划: {plan}");
 
         if !args.yes {
             return Ok(());
         }
 
-        bili_video::transcode_1080(&args.path, &to)?;
+        bili_video::transcode_with_plan(&args.path, &to, &profile, &plan)?;
 
         if let Some(settings) = episode_settings {
             // 判断是否保留片头
@@ -179,6 +195,7 @@ impl Trans for Mp41080Trans {
                     let temp_path = to.with_extension("need-remove.mp4");
                     fs::rename(&to, &temp_path)?;
                     let r = Remover::new(&temp_path, exclude);
+                    warn_out_of_range(&r, &ep.get_full_title()?)?;
                     r.output(to)?;
                     fs::remove_file(&temp_path)?;
                 }
@@ -192,9 +209,8 @@ fn get_trans(action: &str) -> Option<Box<dyn Trans>> {
     match action {
         "mp3" => Some(Box::new(Mp3Trans {})),
         "mp4" => Some(Box::new(Mp4Trans {})),
-        "1080p" => Some(Box::new(Mp41080Trans {})),
         "m3u8" => Some(Box::new(M3U8Trans {})),
-        _ => None,
+        _ => Some(Box::new(ProfileTrans { profile: action.to_string() })),
     }
 }
 
@@ -204,7 +220,6 @@ fn trans_to_episode(args: &TransArgs) -> Result<EpisodeArgs> {
     let mut title = args.title.clone();
     let mut season = args.season;
     let mut episode = args.episode;
-    let mut type_ = args.type_.clone();
     let mut name = String::new();
 
     if let Some(ep) = ep_opt {
@@ -229,13 +244,14 @@ fn trans_to_episode(args: &TransArgs) -> Result<EpisodeArgs> {
         episode = args.episode
     }
 
-    if season > 1000 {
-        type_ = String::from("电影");
+    // 旧规则：没有指定类型时，季数是年份的视频都在 movie 媒体配置中
+    let guess = MediaKind::guess(season);
+    if args.type_.is_none() && guess.is_some() {
         name = String::from("movie");
     }
 
     let ep = EpisodeArgs::new(
-        type_,
+        args.type_,
         Some(name),
         title,
         season,
@@ -259,7 +275,7 @@ mod tests {
         TransArgs {
             path: path.to_string(),
             action: "1080p".to_string(),
-            type_: "电视剧".to_string(),
+            type_: None,
             title: title.to_string(),
             name: String::new(),
             season,
@@ -267,6 +283,7 @@ mod tests {
             yes: false,
             to: None,
             is_reserve: false,
+            target_size: None,
         }
     }
 
@@ -171,7 +171,7 @@ impl UploadArgs {
         self.ep = ep.clone();
         self
     }
-    pub fn get_upload_title(&self) -> String {
+    pub fn get_upload_title(&self) -> Result<String> {
         self.ep.get_full_title()
     }
 }
@@ -231,13 +231,13 @@ pub fn upload(args: UploadArgs) -> anyhow::Result<()> {
         if args.upload.with_append {
             // 上传整集，第一个视频指定标题
             if i == 0 {
-                cmds = upload.to_cmds(false, Some(&args.get_upload_title()))?;
+                cmds = upload.to_cmds(false, Some(&args.get_upload_title()?))?;
             } else {
                 cmds = upload.to_cmds(true, None)?;
             }
         }
 
-        let results = lazycmd::spawn(cmds)?;
+        let results = bili_video::spawn(cmds)?;
         // println!("{results:#?}");
 
         if upload.vid.is_empty() {
@@ -270,7 +270,7 @@ pub fn upload(args: UploadArgs) -> anyhow::Result<()> {
         "1".to_string(),
     ];
     println!("更新视频: {cmds:?}");
-    lazycmd::spawn(cmds)?;
+    bili_video::spawn(cmds)?;
     // 打印视频列表
     let cmds = vec![
         "bili-cli".to_string(),
@@ -279,7 +279,7 @@ pub fn upload(args: UploadArgs) -> anyhow::Result<()> {
         up.mid.to_string()
     ];
     println!("展示视频: {cmds:?}");
-    lazycmd::spawn(cmds)?;
+    bili_video::spawn(cmds)?;
 
     Ok(())
 }
@@ -321,7 +321,7 @@ mod tests {
             "-e", "2",
         ]).unwrap();
         args.fill(&media);
-        assert_eq!(args.get_upload_title(), "多媒体S01E02");
+        assert_eq!(args.get_upload_title().unwrap(), "多媒体S01E02");
 
         let mut args = UploadArgs::try_parse_from([
             "test",
@@ -331,6 +331,6 @@ mod tests {
             "--type", "电影",
         ]).unwrap();
         args.fill(&media);
-        assert_eq!(args.get_upload_title(), "电影标题.2020.06211");
+        assert_eq!(args.get_upload_title().unwrap(), "电影标题.2020.06211");
     }
 }
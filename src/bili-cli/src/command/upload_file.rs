@@ -38,7 +38,7 @@ pub fn upload_file(args: UploadFileArgs) -> Result<()> {
     }
 
     let cmds = upload.to_cmds(false)?;
-    lazycmd::spawn(cmds)?;
+    bili_video::spawn(cmds)?;
 
     Ok(())
 }
@@ -1,10 +1,12 @@
 mod cache;
 mod cli;
+mod progress;
 pub mod command;
 
 pub use cache::{
     create_cache_dir
 };
+pub use progress::init_progress_bar;
 pub use cli::{
     run, Cli,
 };
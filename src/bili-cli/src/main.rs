@@ -1,5 +1,6 @@
 use std::{process, time::Instant};
 use bili_cli::{run, Cli};
+use bili_video::ProcessError;
 use clap::Parser;
 
 
@@ -7,7 +8,18 @@ use clap::Parser;
 async fn main() {
     let cli = Cli::parse();
     let start = Instant::now();
+
+    // Ctrl-C 时终止子进程并清理未完成的文件
+    ctrlc::set_handler(|| {
+        eprintln!("\n收到中断信号，正在取消...");
+        bili_video::cancel_token().cancel();
+    }).expect("Failed set ctrl-c handler");
+
     if let Err(e) = run(cli) {
+        if let Some(ProcessError::Cancelled) = e.downcast_ref::<ProcessError>() {
+            eprintln!("已取消，未完成的文件已清理");
+            process::exit(130);
+        }
         eprintln!("Error: {e}");
         process::exit(1);
     }
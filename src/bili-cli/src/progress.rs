@@ -0,0 +1,40 @@
+use std::io::{self, Write};
+
+use bili_video::{float_to_time_format, Progress};
+
+const BAR_WIDTH: usize = 30;
+
+/// 在终端展示 ffmpeg 进度条
+pub fn init_progress_bar() {
+    bili_video::set_progress_handler(|p| {
+        let mut stderr = io::stderr();
+        let _ = write!(stderr, "\r{}", format_progress(p));
+        if p.done {
+            let _ = writeln!(stderr);
+        }
+        let _ = stderr.flush();
+    });
+}
+
+fn format_progress(p: &Progress) -> String {
+    let speed = p.speed.map(|s| format!("{:.2}x", s)).unwrap_or_else(|| "-".into());
+    match (p.percent(), p.total) {
+        (Some(percent), Some(total)) => {
+            let filled = (percent / 100.0 * BAR_WIDTH as f64) as usize;
+            let eta = p.eta()
+                .map(|d| float_to_time_format(d.as_secs_f64()))
+                .unwrap_or_else(|| "--:--:--".into());
+            format!(
+                "[{}{}] {:5.1}% {}/{} {} ETA {}",
+                "#".repeat(filled),
+                "-".repeat(BAR_WIDTH - filled),
+                percent,
+                float_to_time_format(p.out_time),
+                float_to_time_format(total),
+                speed,
+                eta,
+            )
+        }
+        _ => format!("frame={} time={} {}", p.frame, float_to_time_format(p.out_time), speed),
+    }
+}
@@ -0,0 +1,86 @@
+use std::{fs, path::Path};
+
+use anyhow::{anyhow, Result};
+use bili_video::{format_timestamp, Segment};
+use toml_edit::{value, Array, ArrayOfTables, DocumentMut, Item, Table};
+
+/// 把片段写入媒体配置中对应剧集的 `exclude_segments`，没有该剧集时新建 `[[episodes]]`
+///
+/// 使用 `toml_edit` 修改，保留原文件的注释和格式
+pub fn write_exclude_segments<P: AsRef<Path>>(
+    path: P,
+    season: u16,
+    episode: u16,
+    segments: &[Segment],
+) -> Result<()> {
+    let text = fs::read_to_string(&path)?;
+    let mut doc: DocumentMut = text.parse()?;
+
+    let episodes = doc
+        .entry("episodes")
+        .or_insert(Item::ArrayOfTables(ArrayOfTables::new()))
+        .as_array_of_tables_mut()
+        .ok_or(anyhow!("episodes 必须写作 [[episodes]]"))?;
+
+    let is_match = |table: &Table, key: &str, v: u16| {
+        table.get(key).and_then(|x| x.as_integer()) == Some(v as i64)
+    };
+    let index = episodes
+        .iter()
+        .position(|x| is_match(x, "season", season) && is_match(x, "episode", episode));
+    let index = match index {
+        Some(index) => index,
+        None => {
+            let mut table = Table::new();
+            table["season"] = value(season as i64);
+            table["episode"] = value(episode as i64);
+            episodes.push(table);
+            episodes.len() - 1
+        }
+    };
+
+    let mut array = Array::new();
+    for segment in segments {
+        let mut pair = Array::new();
+        pair.push(format_timestamp(segment.start));
+        pair.push(format_timestamp(segment.end));
+        array.push(pair);
+    }
+    array.fmt();
+    let table = episodes.get_mut(index).expect("episode index out of range");
+    table["exclude_segments"] = value(array);
+
+    fs::write(&path, doc.to_string())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use bili_video::Segment;
+
+    use crate::MediaSettings;
+
+    use super::write_exclude_segments;
+
+    #[test]
+    fn test_write_exclude_segments() {
+        let path = env::temp_dir().join("bili-media-detect.toml");
+        fs::copy("examples/media.toml", &path).unwrap();
+
+        let segments = vec![Segment::new(0.0, 90.2), Segment::new(2610.0, 2700.0)];
+        write_exclude_segments(&path, 1, 2, &segments).unwrap();
+        write_exclude_segments(&path, 1, 3, &segments[..1]).unwrap();
+
+        let text = fs::read_to_string(&path).unwrap();
+        // 注释保留
+        assert!(text.contains("# episode"));
+        assert!(text.contains(r#"exclude_segments = [["00:00:00.000", "00:01:30.200"], ["00:43:30.000", "00:45:00.000"]]"#));
+
+        let media = MediaSettings::from_path(&path).unwrap();
+        assert_eq!(media.get_episode(1, 2).unwrap().exclude_segments, Some(segments.clone()));
+        assert_eq!(media.get_episode(1, 3).unwrap().exclude_segments, Some(segments[..1].to_vec()));
+        fs::remove_file(path).unwrap();
+    }
+}
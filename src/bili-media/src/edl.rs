@@ -0,0 +1,404 @@
+//! 剪辑决策列表（EDL）
+//!
+//! 按顺序记录每个片段的来源、入点、出点和备注，支持三种格式，按扩展名区分：
+//!
+//! - `.json`：`[{ "source": "a.mp4", "in": 10.0, "out": "00:01:35", "label": "开场" }]`
+//! - `.csv`：表头为 `source,in,out,label`
+//! - `.edl`：CMX3600，片段来源写在 `* FROM CLIP NAME:`，备注写在 `* COMMENT:`
+use std::{fmt::Write as _, fs, path::Path};
+
+use anyhow::{anyhow, Result};
+use bili_video::{format_timestamp, parse_timestamp, Segment, Timestamp};
+use serde::{Deserialize, Serialize};
+
+use crate::MarkPart;
+
+/// CMX3600 默认帧率
+pub const EDL_FPS: f64 = 25.0;
+
+/// EDL 中的一个片段
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "EdlRecord", into = "EdlRecord")]
+pub struct EdlEntry {
+    /// 片段来源，片段库 ID 或文件路径，`None` 时使用命令指定的视频
+    pub source: Option<String>,
+    pub segment: Segment,
+    pub label: Option<String>,
+}
+
+impl EdlEntry {
+    pub fn new(source: Option<String>, segment: Segment) -> Self {
+        Self { source, segment, label: None }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct EdlRecord {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    source: Option<String>,
+    #[serde(rename = "in")]
+    start: Timestamp,
+    #[serde(rename = "out")]
+    end: Timestamp,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    label: Option<String>,
+}
+
+impl From<EdlRecord> for EdlEntry {
+    fn from(x: EdlRecord) -> Self {
+        Self { source: x.source, segment: Segment::new(x.start.0, x.end.0), label: x.label }
+    }
+}
+
+impl From<EdlEntry> for EdlRecord {
+    fn from(x: EdlEntry) -> Self {
+        Self {
+            source: x.source,
+            start: Timestamp(x.segment.start),
+            end: Timestamp(x.segment.end),
+            label: x.label,
+        }
+    }
+}
+
+/// EDL 文件格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdlFormat {
+    Json,
+    Csv,
+    Cmx3600,
+}
+
+impl EdlFormat {
+    /// 按扩展名判断格式
+    ///
+    /// Examples
+    ///
+    /// ```
+    /// use media::EdlFormat;
+    ///
+    /// assert_eq!(EdlFormat::from_path("cut.edl").unwrap(), EdlFormat::Cmx3600);
+    /// assert_eq!(EdlFormat::from_path("cut.CSV").unwrap(), EdlFormat::Csv);
+    /// assert!(EdlFormat::from_path("cut.txt").is_err());
+    /// ```
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let ext = path.as_ref().extension().and_then(|x| x.to_str()).unwrap_or_default();
+        match ext.to_lowercase().as_str() {
+            "json" => Ok(Self::Json),
+            "csv" => Ok(Self::Csv),
+            "edl" => Ok(Self::Cmx3600),
+            _ => Err(anyhow!("{:?} 不是支持的 EDL 格式，可选 json、csv、edl", path.as_ref())),
+        }
+    }
+}
+
+/// 剪辑决策列表，片段按顺序拼接
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Edl {
+    pub title: Option<String>,
+    pub entries: Vec<EdlEntry>,
+}
+
+impl Edl {
+    pub fn new(entries: Vec<EdlEntry>) -> Self {
+        Self { title: None, entries }
+    }
+
+    /// 读取 EDL 文件，按扩展名判断格式
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let text = fs::read_to_string(&path)?;
+        let edl = Self::parse(&text, EdlFormat::from_path(&path)?)?;
+        for entry in &edl.entries {
+            entry.segment.validate().map_err(|e| anyhow!("{:?} {}", path.as_ref(), e))?;
+        }
+        Ok(edl)
+    }
+
+    /// 写入 EDL 文件，按扩展名判断格式
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        fs::write(&path, self.to_string(EdlFormat::from_path(&path)?)?)?;
+        Ok(())
+    }
+
+    pub fn parse(text: &str, format: EdlFormat) -> Result<Self> {
+        match format {
+            EdlFormat::Json => Ok(Self::new(serde_json::from_str(text)?)),
+            EdlFormat::Csv => Self::parse_csv(text),
+            EdlFormat::Cmx3600 => Self::parse_cmx(text, EDL_FPS),
+        }
+    }
+
+    pub fn to_string(&self, format: EdlFormat) -> Result<String> {
+        match format {
+            EdlFormat::Json => Ok(serde_json::to_string_pretty(&self.entries)?),
+            EdlFormat::Csv => Ok(self.to_csv()),
+            EdlFormat::Cmx3600 => Ok(self.to_cmx(EDL_FPS)),
+        }
+    }
+
+    /// 所有片段的时间，忽略来源
+    pub fn segments(&self) -> Vec<Segment> {
+        self.entries.iter().map(|x| x.segment).collect()
+    }
+
+    /// 转为 `mark` 使用的片段，没有来源的片段使用 `default_source`
+    ///
+    /// Examples
+    ///
+    /// ```
+    /// use bili_video::Segment;
+    /// use media::{Edl, EdlEntry, MarkPart};
+    ///
+    /// let edl = Edl::new(vec![
+    ///     EdlEntry::new(Some("爱2.14.3".to_string()), Segment::new(10.0, 95.0)),
+    ///     EdlEntry::new(None, Segment::new(0.0, 30.0)),
+    /// ]);
+    /// let parts = edl.parts(Some("trailer.mp4")).unwrap();
+    /// assert_eq!(parts[1], MarkPart { id: "trailer.mp4".to_string(), segment: Some(Segment::new(0.0, 30.0)) });
+    /// assert!(edl.parts(None).is_err());
+    /// ```
+    pub fn parts(&self, default_source: Option<&str>) -> Result<Vec<MarkPart>> {
+        self.entries
+            .iter()
+            .map(|x| {
+                let id = x.source.as_deref().or(default_source).ok_or(anyhow!("片段 {} 没有来源", x.segment))?;
+                Ok(MarkPart { id: id.to_string(), segment: Some(x.segment) })
+            })
+            .collect()
+    }
+
+    /// 解析 CSV，表头为 `source,in,out,label`，时间可以是秒数或 `hh:mm:ss.mmm`
+    ///
+    /// Examples
+    ///
+    /// ```
+    /// use bili_video::Segment;
+    /// use media::Edl;
+    ///
+    /// let edl = Edl::parse_csv("\
+    /// source,in,out,label
+    /// \"a,b.mp4\",10,00:01:35,开场
+    /// ,120.5,130,
+    /// ").unwrap();
+    /// assert_eq!(edl.entries[0].source, Some("a,b.mp4".to_string()));
+    /// assert_eq!(edl.entries[0].label, Some("开场".to_string()));
+    /// assert_eq!(edl.segments(), vec![Segment::new(10.0, 95.0), Segment::new(120.5, 130.0)]);
+    /// ```
+    pub fn parse_csv(text: &str) -> Result<Self> {
+        let mut entries = Vec::new();
+        for (index, line) in text.lines().enumerate() {
+            if line.trim().is_empty() || (index == 0 && line.starts_with("source")) {
+                continue;
+            }
+            let fields = split_csv(line);
+            if fields.len() < 3 {
+                return Err(anyhow!("第 {} 行应该至少有 source,in,out 三列", index + 1));
+            }
+            let text = |i: usize| fields.get(i).filter(|x| !x.is_empty()).cloned();
+            let start = parse_timestamp(&fields[1])?;
+            let end = parse_timestamp(&fields[2])?;
+            entries.push(EdlEntry { source: text(0), segment: Segment::new(start, end), label: text(3) });
+        }
+        Ok(Self::new(entries))
+    }
+
+    fn to_csv(&self) -> String {
+        let mut text = String::from("source,in,out,label\n");
+        for x in &self.entries {
+            let _ = writeln!(
+                text,
+                "{},{},{},{}",
+                quote_csv(x.source.as_deref().unwrap_or_default()),
+                format_timestamp(x.segment.start),
+                format_timestamp(x.segment.end),
+                quote_csv(x.label.as_deref().unwrap_or_default()),
+            );
+        }
+        text
+    }
+
+    /// 解析 CMX3600，同一编号的音频和视频事件只保留一个
+    ///
+    /// Examples
+    ///
+    /// ```
+    /// use bili_video::Segment;
+    /// use media::Edl;
+    ///
+    /// let edl = Edl::parse_cmx("\
+    /// TITLE: 断网穿越主线
+    /// FCM: NON-DROP FRAME
+    ///
+    /// 001  AX       V     C        00:00:10:00 00:01:35:00 00:00:00:00 00:01:25:00
+    /// 001  AX       A     C        00:00:10:00 00:01:35:00 00:00:00:00 00:01:25:00
+    /// * FROM CLIP NAME: 爱2.14.3.ts
+    /// * COMMENT: 开场
+    /// 002  AX       V     C        00:02:00:12 00:02:10:00 00:01:25:00 00:01:34:13
+    /// ", 25.0).unwrap();
+    /// assert_eq!(edl.title, Some("断网穿越主线".to_string()));
+    /// assert_eq!(edl.entries[0].source, Some("爱2.14.3.ts".to_string()));
+    /// assert_eq!(edl.entries[0].label, Some("开场".to_string()));
+    /// assert_eq!(edl.entries[1].source, None);
+    /// assert_eq!(edl.segments(), vec![Segment::new(10.0, 95.0), Segment::new(120.48, 130.0)]);
+    /// ```
+    pub fn parse_cmx(text: &str, fps: f64) -> Result<Self> {
+        let mut edl = Self::default();
+        let mut last_event = None;
+        for line in text.lines() {
+            let line = line.trim();
+            if let Some(title) = line.strip_prefix("TITLE:") {
+                edl.title = Some(title.trim().to_string());
+            } else if let Some(name) = line.strip_prefix("* FROM CLIP NAME:") {
+                if let Some(entry) = edl.entries.last_mut() {
+                    entry.source = Some(name.trim().to_string());
+                }
+            } else if let Some(comment) = line.strip_prefix("* COMMENT:") {
+                if let Some(entry) = edl.entries.last_mut() {
+                    entry.label = Some(comment.trim().to_string());
+                }
+            } else if line.starts_with(|c: char| c.is_ascii_digit()) {
+                let fields: Vec<&str> = line.split_whitespace().collect();
+                if last_event == fields.first().copied() {
+                    continue;
+                }
+                last_event = fields.first().copied();
+                if fields.len() < 8 {
+                    return Err(anyhow!("无效的 EDL 事件: {}", line));
+                }
+                // 转场为 D 时多一列时长，取最后四个时间码
+                let timecodes = &fields[fields.len() - 4..];
+                let start = parse_timecode(timecodes[0], fps)?;
+                let end = parse_timecode(timecodes[1], fps)?;
+                edl.entries.push(EdlEntry::new(None, Segment::new(start, end)));
+            }
+        }
+        Ok(edl)
+    }
+
+    fn to_cmx(&self, fps: f64) -> String {
+        let mut text = format!("TITLE: {}\nFCM: NON-DROP FRAME\n\n", self.title.as_deref().unwrap_or("bilibili"));
+        let mut record = 0.0;
+        for (i, x) in self.entries.iter().enumerate() {
+            let duration = x.segment.duration();
+            let _ = writeln!(
+                text,
+                "{:03}  AX       AA/V  C        {} {} {} {}",
+                i + 1,
+                format_timecode(x.segment.start, fps),
+                format_timecode(x.segment.end, fps),
+                format_timecode(record, fps),
+                format_timecode(record + duration, fps),
+            );
+            if let Some(source) = &x.source {
+                let _ = writeln!(text, "* FROM CLIP NAME: {}", source);
+            }
+            if let Some(label) = &x.label {
+                let _ = writeln!(text, "* COMMENT: {}", label);
+            }
+            record += duration;
+        }
+        text
+    }
+}
+
+/// `hh:mm:ss:ff` 时间码转为秒数
+///
+/// 时间码按整数的名义帧率计数，29.97 按 30 帧计算，和 [`format_timecode`] 一致
+fn parse_timecode(s: &str, fps: f64) -> Result<f64> {
+    // 丢帧时间码使用 `;` 分隔帧数
+    let parts: Vec<u64> = s
+        .split([':', ';'])
+        .map(|x| x.parse().map_err(|_| anyhow!("无效的时间码: {}", s)))
+        .collect::<Result<_>>()?;
+    if parts.len() != 4 {
+        return Err(anyhow!("无效的时间码: {}", s));
+    }
+    let seconds = (parts[0] * 3600 + parts[1] * 60 + parts[2]) as f64 + parts[3] as f64 / fps.round();
+    Ok((seconds * 1000.0).round() / 1000.0)
+}
+
+/// 秒数转为 `hh:mm:ss:ff` 时间码，帧数按名义帧率计算
+fn format_timecode(seconds: f64, fps: f64) -> String {
+    let per_second = fps.round() as u64;
+    let frames = (seconds * per_second as f64).round() as u64;
+    let total = frames / per_second;
+    format!(
+        "{:02}:{:02}:{:02}:{:02}",
+        total / 3600,
+        total % 3600 / 60,
+        total % 60,
+        frames % per_second
+    )
+}
+
+/// 按逗号分割，支持双引号包含逗号和 `""` 转义
+fn split_csv(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields.into_iter().map(|x| x.trim().to_string()).collect()
+}
+
+fn quote_csv(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use bili_video::Segment;
+
+    use super::{format_timecode, parse_timecode, Edl, EdlEntry};
+
+    #[test]
+    fn test_timecode() {
+        assert_eq!(format_timecode(95.48, 25.0), "00:01:35:12");
+        assert_eq!(parse_timecode("00:01:35:12", 25.0).unwrap(), 95.48);
+        assert_eq!(format_timecode(3600.0, 29.97), "01:00:00:00");
+        assert_eq!(parse_timecode("01:00:00:00", 29.97).unwrap(), 3600.0);
+        assert_eq!(parse_timecode(&format_timecode(95.5, 29.97), 29.97).unwrap(), 95.5);
+        assert!(parse_timecode("00:01:35", 25.0).is_err());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut first = EdlEntry::new(Some("爱2.14.3".to_string()), Segment::new(10.0, 95.48));
+        first.label = Some("开场, \"断网\"".to_string());
+        let mut edl = Edl::new(vec![first, EdlEntry::new(None, Segment::new(120.0, 130.0))]);
+        edl.title = Some("断网穿越主线".to_string());
+
+        for ext in ["json", "csv", "edl"] {
+            let path = env::temp_dir().join(format!("bili-media-edl-test.{}", ext));
+            edl.write(&path).unwrap();
+            let read = Edl::from_path(&path).unwrap();
+            assert_eq!(read.entries, edl.entries, "{}", ext);
+            fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_invalid_segment() {
+        let path = env::temp_dir().join("bili-media-edl-invalid.json");
+        fs::write(&path, r#"[{ "in": 20, "out": "00:00:10" }]"#).unwrap();
+        assert!(Edl::from_path(&path).is_err());
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,156 @@
+use std::{fmt, str::FromStr};
+
+use anyhow::{anyhow, Result};
+use schemars::{gen::SchemaGenerator, schema::Schema, JsonSchema};
+use serde::{de, Deserialize, Deserializer};
+use serde_json::json;
+
+/// 选择多集，在 TOML 中可以写作范围、列表或两者混合
+///
+/// ```toml
+/// episodes = "1-12"
+/// episodes = [1, 3, 5]
+/// episodes = ["1-6", 9]
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Episodes(Vec<(u16, u16)>);
+
+impl Episodes {
+    /// 是否包含第 `episode` 集
+    ///
+    /// Examples
+    ///
+    /// ```
+    /// use media::Episodes;
+    ///
+    /// let episodes: Episodes = "1-6,9".parse().unwrap();
+    /// assert!(episodes.contains(6));
+    /// assert!(episodes.contains(9));
+    /// assert!(!episodes.contains(7));
+    /// ```
+    pub fn contains(&self, episode: u16) -> bool {
+        self.0.iter().any(|(start, end)| (*start..=*end).contains(&episode))
+    }
+
+    /// 包含的集数，集数越少越具体
+    ///
+    /// Examples
+    ///
+    /// ```
+    /// use media::Episodes;
+    ///
+    /// assert_eq!("1-12".parse::<Episodes>().unwrap().len(), 12);
+    /// assert_eq!("1-6,5-9".parse::<Episodes>().unwrap().len(), 9);
+    /// ```
+    pub fn len(&self) -> usize {
+        let mut ranges = self.0.clone();
+        ranges.sort();
+        let mut count = 0;
+        let mut next = 0;
+        for (start, end) in ranges {
+            let start = (start as usize).max(next);
+            if end as usize >= start {
+                count += end as usize - start + 1;
+                next = end as usize + 1;
+            }
+        }
+        count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn push(&mut self, s: &str) -> Result<()> {
+        let parse = |x: &str| x.trim().parse::<u16>().map_err(|_| anyhow!("{} 不是有效的集数", s));
+        let range = match s.split_once('-') {
+            Some((start, end)) => (parse(start)?, parse(end)?),
+            None => (parse(s)?, parse(s)?),
+        };
+        if range.0 > range.1 {
+            return Err(anyhow!("{} 的开始集数大于结束集数", s));
+        }
+        self.0.push(range);
+        Ok(())
+    }
+}
+
+impl FromStr for Episodes {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut episodes = Self(Vec::new());
+        for part in s.split(',').filter(|x| !x.trim().is_empty()) {
+            episodes.push(part)?;
+        }
+        if episodes.is_empty() {
+            return Err(anyhow!("{} 没有选择任何一集", s));
+        }
+        Ok(episodes)
+    }
+}
+
+impl fmt::Display for Episodes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parts: Vec<String> = self
+            .0
+            .iter()
+            .map(|(start, end)| if start == end { start.to_string() } else { format!("{}-{}", start, end) })
+            .collect();
+        write!(f, "{}", parts.join(","))
+    }
+}
+
+/// 集数或范围字符串
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum EpisodeValue {
+    Number(u16),
+    Text(String),
+}
+
+impl EpisodeValue {
+    fn to_text(&self) -> String {
+        match self {
+            Self::Number(x) => x.to_string(),
+            Self::Text(x) => x.clone(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum EpisodesValue {
+    One(EpisodeValue),
+    List(Vec<EpisodeValue>),
+}
+
+impl<'de> Deserialize<'de> for Episodes {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = match EpisodesValue::deserialize(deserializer)? {
+            EpisodesValue::One(x) => x.to_text(),
+            EpisodesValue::List(xs) => xs.iter().map(|x| x.to_text()).collect::<Vec<_>>().join(","),
+        };
+        text.parse().map_err(de::Error::custom)
+    }
+}
+
+impl JsonSchema for Episodes {
+    fn schema_name() -> String {
+        "Episodes".to_string()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        let item = json!({
+            "anyOf": [
+                { "type": "integer", "minimum": 0 },
+                { "type": "string", "pattern": "^\\s*\\d+\\s*(-\\s*\\d+\\s*)?(,\\s*\\d+\\s*(-\\s*\\d+\\s*)?)*$" },
+            ],
+        });
+        let schema = json!({
+            "description": "集数范围或列表，如 \"1-12\"、[1, 3, 5]",
+            "anyOf": [item, { "type": "array", "items": item }],
+        });
+        serde_json::from_value(schema).expect("invalid schema")
+    }
+}
@@ -0,0 +1,189 @@
+//! 媒体配置的 `extends` 和 `include`
+//!
+//! ```toml
+//! extends = "sitcom-defaults"
+//! include = ["common.toml"]
+//! ```
+//!
+//! 引用的文件和当前文件在同一目录，名称没有 `.toml` 时自动补上。按 `extends`、`include`、
+//! 当前文件的顺序合并，后面的覆盖前面的：表递归合并，`[[episodes]]` 等表数组中季、集、
+//! `id` 或 `name` 相同的表合并，其余追加，其他值直接替换
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Result};
+use toml::{Table, Value};
+
+/// 表数组中用来判断是否为同一项的字段
+const KEY_FIELDS: [&str; 5] = ["season", "episode", "episodes", "id", "name"];
+
+/// 读取媒体配置并合并引用的文件
+pub fn resolve<P: AsRef<Path>>(path: P) -> Result<Table> {
+    resolve_checked(path, &|_| Ok(()))
+}
+
+/// 读取媒体配置并合并引用的文件，合并前用 `check` 检查每个文件的内容，错误中带有文件路径
+pub fn resolve_checked<P: AsRef<Path>>(path: P, check: &dyn Fn(&str) -> Result<()>) -> Result<Table> {
+    resolve_with(path.as_ref(), check, &mut Vec::new())
+}
+
+/// 媒体配置直接引用的文件
+pub fn references<P: AsRef<Path>>(path: P) -> Result<Vec<PathBuf>> {
+    let mut table = read_table(path.as_ref())?;
+    take_references(path.as_ref(), &mut table)
+}
+
+fn resolve_with(path: &Path, check: &dyn Fn(&str) -> Result<()>, stack: &mut Vec<PathBuf>) -> Result<Table> {
+    let canonical = path.canonicalize().map_err(|e| anyhow!("{:?}: {}", path, e))?;
+    if stack.contains(&canonical) {
+        return Err(anyhow!("{:?} 循环引用", path));
+    }
+    stack.push(canonical);
+
+    let text = fs::read_to_string(path).map_err(|e| anyhow!("{:?}: {}", path, e))?;
+    check(&text).map_err(|e| anyhow!("{:?}: {}", path, e))?;
+    let mut own: Table = text.parse().map_err(|e| anyhow!("{:?}: {}", path, e))?;
+    let mut merged = Table::new();
+    for reference in take_references(path, &mut own)? {
+        let base = resolve_with(&reference, check, stack)?;
+        merge(&mut merged, base);
+    }
+    merge(&mut merged, own);
+
+    stack.pop();
+    Ok(merged)
+}
+
+fn read_table(path: &Path) -> Result<Table> {
+    let text = fs::read_to_string(path).map_err(|e| anyhow!("{:?}: {}", path, e))?;
+    text.parse().map_err(|e| anyhow!("{:?}: {}", path, e))
+}
+
+/// 取出 `extends` 和 `include`，返回引用文件的路径
+fn take_references(path: &Path, table: &mut Table) -> Result<Vec<PathBuf>> {
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let to_path = |value: &Value| -> Result<PathBuf> {
+        let name = value.as_str().ok_or(anyhow!("{:?} extends 和 include 只能填写文件名", path))?;
+        let name = if name.ends_with(".toml") { name.to_string() } else { format!("{}.toml", name) };
+        Ok(dir.join(name))
+    };
+
+    let mut paths = Vec::new();
+    if let Some(value) = table.remove("extends") {
+        paths.push(to_path(&value)?);
+    }
+    match table.remove("include") {
+        Some(Value::Array(values)) => {
+            for value in &values {
+                paths.push(to_path(value)?);
+            }
+        }
+        Some(value) => paths.push(to_path(&value)?),
+        None => {}
+    }
+    Ok(paths)
+}
+
+/// 把 `other` 合并到 `base`，`other` 优先
+fn merge(base: &mut Table, other: Table) {
+    for (key, value) in other {
+        let rest = match (base.get_mut(&key), value) {
+            (Some(Value::Table(a)), Value::Table(b)) => {
+                merge(a, b);
+                None
+            }
+            (Some(Value::Array(a)), Value::Array(b)) if is_tables(a) && is_tables(&b) => {
+                for item in b {
+                    match (a.iter().position(|x| same_item(x, &item)), item) {
+                        (Some(i), Value::Table(y)) => {
+                            if let Value::Table(x) = &mut a[i] {
+                                merge(x, y);
+                            }
+                        }
+                        (_, item) => a.push(item),
+                    }
+                }
+                None
+            }
+            (_, value) => Some(value),
+        };
+        if let Some(value) = rest {
+            base.insert(key, value);
+        }
+    }
+}
+
+fn is_tables(values: &[Value]) -> bool {
+    !values.is_empty() && values.iter().all(|x| x.is_table())
+}
+
+fn same_item(a: &Value, b: &Value) -> bool {
+    KEY_FIELDS.iter().all(|key| a.get(key) == b.get(key))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use toml::Value;
+
+    use crate::MediaSettings;
+
+    use super::{references, resolve};
+
+    #[test]
+    fn test_resolve() {
+        let dir = env::temp_dir().join("bili-media-include-test");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("sitcom-defaults.toml"), r#"
+suffix_parts = ["ipartment"]
+
+[[uploaders]]
+tag = "电视剧,情景喜剧"
+
+[[spliters]]
+count = 3
+screenshot_seconds = [10, 20]
+"#).unwrap();
+        fs::write(dir.join("common.toml"), r#"
+[[spliters]]
+count = 4
+"#).unwrap();
+        fs::write(dir.join("show.toml"), r#"
+extends = "sitcom-defaults"
+include = ["common.toml"]
+name = "show"
+title = "情景喜剧"
+
+[[uploaders]]
+season = 2
+tag = "电视剧,情景喜剧,第二季"
+"#).unwrap();
+
+        assert_eq!(references(dir.join("show.toml")).unwrap().len(), 2);
+        let table = resolve(dir.join("show.toml")).unwrap();
+        assert!(table.get("extends").is_none());
+
+        let media: MediaSettings = Value::Table(table).try_into().unwrap();
+        assert_eq!(media.suffix_parts, Some(vec!["ipartment".to_string()]));
+        // 相同的默认配置合并，include 覆盖 extends
+        let spliters = media.spliters.unwrap();
+        assert_eq!(spliters.len(), 1);
+        assert_eq!(spliters[0].count, Some(4));
+        assert_eq!(spliters[0].screenshot_seconds, Some(vec![10, 20]));
+        assert_eq!(media.uploaders.unwrap().len(), 2);
+
+        // 引用文件中的未知字段报告所在文件和行号
+        fs::write(dir.join("common.toml"), "\n[[spliters]]\nremove_parts = [[0, 90]]\n").unwrap();
+        let message = MediaSettings::resolve(dir.join("show.toml")).unwrap_err().to_string();
+        assert!(message.contains("common.toml"));
+        assert!(message.contains("line 3"));
+
+        // 循环引用
+        fs::write(dir.join("common.toml"), "include = [\"show\"]\n").unwrap();
+        assert!(resolve(dir.join("show.toml")).is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,252 @@
+//! 媒体类型
+//!
+//! ```toml
+//! kind = "variety"
+//! ```
+//!
+//! 不同类型的媒体用不同的编号，仍然记在季和集中：
+//!
+//! | 类型 | 季 | 集 |
+//! | --- | --- | --- |
+//! | 电视剧 `series` | 季数 | 集数 |
+//! | 电影 `movie` | 年份 | 第几部分 |
+//! | 综艺 `variety` | 播出年份 | 播出日期，如 `621` 表示 6 月 21 日 |
+//! | 纪录片 `documentary` | 季数 | 第几部分 |
+//!
+//! 没有填写类型时，季数大于 1000 的按旧规则视为电影，集数是日期等编号，标题和路径保持旧的格式；
+//! 填写 `kind = "movie"` 时集数总是第几部分
+use std::{fmt, path::PathBuf, str::FromStr};
+
+use anyhow::{anyhow, Result};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+/// 旧配置中季数大于该值时是年份
+const YEAR_SEASON: u16 = 1000;
+
+/// 媒体类型，默认是电视剧
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum MediaKind {
+    #[default]
+    Series,
+    Movie,
+    Variety,
+    Documentary,
+}
+
+impl MediaKind {
+    /// 中文名称，也是媒体目录下的分类目录
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Series => "电视剧",
+            Self::Movie => "电影",
+            Self::Variety => "综艺",
+            Self::Documentary => "纪录片",
+        }
+    }
+
+    /// 没有填写类型时按旧规则推断：季数大于 1000 的是电影
+    ///
+    /// Examples
+    ///
+    /// ```
+    /// use media::MediaKind;
+    ///
+    /// assert_eq!(MediaKind::guess(2009), Some(MediaKind::Movie));
+    /// assert_eq!(MediaKind::guess(2), None);
+    /// ```
+    pub fn guess(season: u16) -> Option<Self> {
+        if season > YEAR_SEASON {
+            Some(Self::Movie)
+        } else {
+            None
+        }
+    }
+
+    /// 检查季和集是否是这个类型的有效编号
+    ///
+    /// Examples
+    ///
+    /// ```
+    /// use media::MediaKind;
+    ///
+    /// assert!(MediaKind::Variety.check(2024, 621).is_ok());
+    /// assert!(MediaKind::Variety.check(2024, 1321).is_err());
+    /// ```
+    pub fn check(&self, season: u16, episode: u16) -> Result<()> {
+        if let MediaId::AirDate { year, month, day } = MediaId::new(*self, season, episode) {
+            if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+                return Err(anyhow!("{}-{:02}-{:02} 不是有效的播出日期", year, month, day));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for MediaKind {
+    type Err = anyhow::Error;
+
+    /// 英文名称和中文名称都可以
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "series" | "电视剧" => Ok(Self::Series),
+            "movie" | "电影" => Ok(Self::Movie),
+            "variety" | "综艺" => Ok(Self::Variety),
+            "documentary" | "纪录片" => Ok(Self::Documentary),
+            _ => Err(anyhow!("未知的媒体类型 {}，可选 series、movie、variety、documentary", s)),
+        }
+    }
+}
+
+impl fmt::Display for MediaKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.label())
+    }
+}
+
+/// 按媒体类型解释的季和集
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaId {
+    /// 电视剧的季和集
+    Episode { season: u16, episode: u16 },
+    /// 电影的年份和第几部分
+    Movie { year: u16, part: u16 },
+    /// 综艺的播出日期
+    AirDate { year: u16, month: u16, day: u16 },
+    /// 纪录片的季和第几部分
+    Part { season: u16, part: u16 },
+    /// 没有填写类型的旧配置电影：季数是年份，集数是日期等编号
+    Legacy { season: u16, episode: u16 },
+}
+
+impl MediaId {
+    /// 填写了类型时的季和集
+    ///
+    /// Examples
+    ///
+    /// ```
+    /// use media::{MediaId, MediaKind};
+    ///
+    /// assert_eq!(MediaId::new(MediaKind::Variety, 2024, 621), MediaId::AirDate { year: 2024, month: 6, day: 21 });
+    /// assert_eq!(MediaId::new(MediaKind::Movie, 2009, 1), MediaId::Movie { year: 2009, part: 1 });
+    /// assert_eq!(MediaId::new(MediaKind::Movie, 2009, 120), MediaId::Movie { year: 2009, part: 120 });
+    /// ```
+    pub fn new(kind: MediaKind, season: u16, episode: u16) -> Self {
+        match kind {
+            MediaKind::Series => Self::Episode { season, episode },
+            MediaKind::Movie => Self::Movie { year: season, part: episode },
+            MediaKind::Variety => Self::AirDate { year: season, month: episode / 100, day: episode % 100 },
+            MediaKind::Documentary => Self::Part { season, part: episode },
+        }
+    }
+
+    /// 没有填写类型时按旧规则推断，季数大于 1000 的是旧配置的电影
+    ///
+    /// Examples
+    ///
+    /// ```
+    /// use media::MediaId;
+    ///
+    /// assert_eq!(MediaId::guess(2009, 1), MediaId::Legacy { season: 2009, episode: 1 });
+    /// assert_eq!(MediaId::guess(2, 3), MediaId::Episode { season: 2, episode: 3 });
+    /// ```
+    pub fn guess(season: u16, episode: u16) -> Self {
+        match MediaKind::guess(season) {
+            Some(_) => Self::Legacy { season, episode },
+            None => Self::Episode { season, episode },
+        }
+    }
+
+    /// `kind` 是配置或参数中填写的类型，没有填写时按旧规则推断
+    pub fn from_kind(kind: Option<MediaKind>, season: u16, episode: u16) -> Self {
+        match kind {
+            Some(kind) => Self::new(kind, season, episode),
+            None => Self::guess(season, episode),
+        }
+    }
+
+    pub fn kind(&self) -> MediaKind {
+        match self {
+            Self::Episode { .. } => MediaKind::Series,
+            Self::Movie { .. } | Self::Legacy { .. } => MediaKind::Movie,
+            Self::AirDate { .. } => MediaKind::Variety,
+            Self::Part { .. } => MediaKind::Documentary,
+        }
+    }
+
+    /// 完整标题，`title` 是剧名，`episode_title` 是这一集的标题
+    ///
+    /// Examples
+    ///
+    /// ```
+    /// use media::{MediaId, MediaKind};
+    ///
+    /// let title = |kind, season, episode, episode_title| MediaId::from_kind(kind, season, episode).title("多媒体", episode_title);
+    /// assert_eq!(title(Some(MediaKind::Series), 1, 2, ""), "多媒体S01E02");
+    /// assert_eq!(title(Some(MediaKind::Movie), 2009, 1, "疯狂的赛车"), "疯狂的赛车.2009");
+    /// assert_eq!(title(Some(MediaKind::Movie), 2009, 2, ""), "多媒体.2009.Part2");
+    /// assert_eq!(title(None, 2009, 1201, "疯狂的赛车"), "疯狂的赛车.2009.01201");
+    /// assert_eq!(title(None, 2009, 1, "疯狂的赛车"), "疯狂的赛车.2009.00001");
+    /// assert_eq!(title(Some(MediaKind::Variety), 2024, 621, "嘉宾"), "多媒体.2024-06-21.嘉宾");
+    /// assert_eq!(title(Some(MediaKind::Documentary), 1, 3, ""), "多媒体.Part03");
+    /// assert_eq!(title(Some(MediaKind::Documentary), 2, 3, ""), "多媒体.S02.Part03");
+    /// ```
+    pub fn title(&self, title: &str, episode_title: &str) -> String {
+        let with_episode_title = |s: String| {
+            if episode_title.is_empty() {
+                s
+            } else {
+                format!("{}.{}", s, episode_title)
+            }
+        };
+        match *self {
+            Self::Episode { season, episode } => format!("{}S{:02}E{:02}", title, season, episode),
+            Self::Movie { year, part } => {
+                let name = if episode_title.is_empty() { title } else { episode_title };
+                if part > 1 {
+                    format!("{}.{}.Part{}", name, year, part)
+                } else {
+                    format!("{}.{}", name, year)
+                }
+            }
+            Self::AirDate { year, month, day } => with_episode_title(format!("{}.{}-{:02}-{:02}", title, year, month, day)),
+            Self::Part { season, part } if season > 1 => with_episode_title(format!("{}.S{:02}.Part{:02}", title, season, part)),
+            Self::Part { part, .. } => with_episode_title(format!("{}.Part{:02}", title, part)),
+            Self::Legacy { season, episode } => format!("{}.{:04}.{:05}", episode_title, season, episode),
+        }
+    }
+
+    /// 相对于媒体目录的视频路径，第一级是类型目录
+    ///
+    /// Examples
+    ///
+    /// ```
+    /// use std::path::PathBuf;
+    ///
+    /// use media::{MediaId, MediaKind};
+    ///
+    /// let path = |kind, season, episode| MediaId::from_kind(kind, season, episode).path("多媒体", "疯狂的赛车");
+    /// assert_eq!(path(Some(MediaKind::Series), 1, 2), PathBuf::from("电视剧/多媒体/多媒体1/S01E02.mp4"));
+    /// assert_eq!(path(Some(MediaKind::Movie), 2009, 1), PathBuf::from("电影/多媒体/疯狂的赛车.2009.mp4"));
+    /// assert_eq!(path(None, 2009, 1201), PathBuf::from("电影/多媒体/多媒体2009/S2009E1201.mp4"));
+    /// assert_eq!(path(None, 2009, 1), PathBuf::from("电影/多媒体/多媒体2009/S2009E01.mp4"));
+    /// assert_eq!(path(Some(MediaKind::Variety), 2024, 621), PathBuf::from("综艺/多媒体/2024/2024-06-21.mp4"));
+    /// assert_eq!(path(Some(MediaKind::Documentary), 1, 3), PathBuf::from("纪录片/多媒体/多媒体1/Part03.mp4"));
+    /// ```
+    pub fn path(&self, title: &str, episode_title: &str) -> PathBuf {
+        let dir = PathBuf::from(self.kind().label()).join(title);
+        match *self {
+            Self::Episode { season, episode } | Self::Legacy { season, episode } => dir
+                .join(format!("{}{}", title, season))
+                .join(format!("S{:02}E{:02}.mp4", season, episode)),
+            Self::Movie { .. } => dir.join(format!("{}.mp4", self.title(title, episode_title))),
+            Self::AirDate { year, month, day } => dir
+                .join(year.to_string())
+                .join(format!("{}-{:02}-{:02}.mp4", year, month, day)),
+            Self::Part { season, part } => dir
+                .join(format!("{}{}", title, season))
+                .join(format!("Part{:02}.mp4", part)),
+        }
+    }
+}
@@ -1,13 +1,26 @@
 mod part;
 mod media;
+mod editor;
+mod edl;
+mod lint;
+mod episodes;
+mod include;
+mod kind;
 
 pub use media::{
     MediaSettings,
     SpliterSettings,
     EpisodeSettings,
     MarkSettings,
+    MarkPart,
 };
 pub use part::{
     init_part,
     get_rand_part_path,
 };
+pub use editor::write_exclude_segments;
+pub use edl::{Edl, EdlEntry, EdlFormat, EDL_FPS};
+pub use lint::Problem;
+pub use episodes::Episodes;
+pub use include::{references, resolve};
+pub use kind::{MediaId, MediaKind};
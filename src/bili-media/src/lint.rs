@@ -0,0 +1,111 @@
+use std::collections::HashSet;
+
+use crate::media::{place, Episode};
+use crate::MediaSettings;
+
+/// 检查媒体配置发现的问题
+#[derive(Debug, Clone, PartialEq)]
+pub struct Problem {
+    /// 问题所在的位置，如 `episodes S01E02`
+    pub place: String,
+    pub message: String,
+    /// 乱序和重叠的片段在使用时自动修正，只作为提示
+    pub fixable: bool,
+}
+
+impl Problem {
+    fn error(place: String, message: String) -> Self {
+        Self { place, message, fixable: false }
+    }
+}
+
+impl MediaSettings {
+    /// 检查片段、重复的 mark ID、重复的季和集、综艺的播出日期，以及不在片段库中的后缀名称
+    ///
+    /// 没有加载 `bilibili.toml` 时不检查后缀名称
+    ///
+    /// Examples
+    ///
+    /// ```
+    /// use media::MediaSettings;
+    ///
+    /// let media = MediaSettings::parse(r#"
+    /// name = "longmen"
+    /// title = "龙门镖局"
+    ///
+    /// [[episodes]]
+    /// season = 1
+    /// episode = 2
+    /// exclude_segments = [[90, 0]]
+    ///
+    /// [[episodes]]
+    /// season = 1
+    /// episode = 2
+    /// "#).unwrap();
+    /// let problems = media.lint();
+    /// assert_eq!(problems.len(), 2);
+    /// assert_eq!(problems[0].place, "episodes S01E02");
+    /// ```
+    pub fn lint(&self) -> Vec<Problem> {
+        let mut problems: Vec<Problem> = self
+            .check_segments()
+            .into_iter()
+            .map(|(place, e)| Problem { place, message: e.to_string(), fixable: e.is_fixable() })
+            .collect();
+
+        problems.extend(duplicates("episodes", &self.episodes));
+        problems.extend(duplicates("trans", &self.trans));
+        problems.extend(duplicates("spliters", &self.spliters));
+        problems.extend(duplicates("uploaders", &self.uploaders));
+
+        if let Some(kind) = self.kind {
+            for x in self.episodes.iter().flatten() {
+                if let (Some(season), Some(episode)) = (x.season, x.episode) {
+                    if let Err(e) = kind.check(season, episode) {
+                        problems.push(Problem::error(place("episodes", x), e.to_string()));
+                    }
+                }
+            }
+        }
+
+        let mut ids = HashSet::new();
+        for mark in self.marks.iter().flatten() {
+            if !ids.insert(&mark.id) {
+                problems.push(Problem::error(format!("marks {}", mark.id), "重复的 mark ID".to_string()));
+            }
+        }
+
+        if let Some(settings) = &self.settings {
+            let names = &settings.part.names;
+            let mut check = |place: String, suffix_parts: &Option<Vec<String>>| {
+                for name in suffix_parts.iter().flatten().filter(|x| !names.contains(x)) {
+                    problems.push(Problem::error(place.clone(), format!("后缀 {} 不在片段库中", name)));
+                }
+            };
+            check("suffix_parts".to_string(), &self.suffix_parts);
+            for x in self.spliters.iter().flatten() {
+                check(place("spliters", x), &x.suffix_parts);
+            }
+            for x in self.marks.iter().flatten() {
+                check(format!("marks {}", x.id), &x.suffix_parts);
+            }
+        }
+        problems
+    }
+}
+
+/// 季和集相同的配置，字段冲突时第一个生效；`episode` 和 `episodes` 不能同时填写
+fn duplicates<T: Episode>(kind: &str, items: &Option<Vec<T>>) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    let mut keys = HashSet::new();
+    for x in items.iter().flatten() {
+        if x.get_episode().is_some() && x.get_episodes().is_some() {
+            problems.push(Problem::error(place(kind, x), "episode 和 episodes 不能同时填写".to_string()));
+        }
+        let key = (x.get_season(), x.get_episode(), x.get_episodes().map(|e| e.to_string()));
+        if !keys.insert(key) {
+            problems.push(Problem::error(place(kind, x), "重复的季和集，字段冲突时第一个生效".to_string()));
+        }
+    }
+    problems
+}
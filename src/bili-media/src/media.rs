@@ -1,29 +1,110 @@
-use std::path::{Path, PathBuf};
+use std::{cmp::Reverse, fs, path::{Path, PathBuf}};
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 
+use bili_video::{
+    check_segments, resolve_profile, BoundaryMode, ProfilePatch, Segment, SegmentError, SplitBy, Timestamp,
+    TranscodeProfile,
+};
+use schemars::{gen::SchemaGenerator, schema::Schema, JsonSchema};
 use serde::Deserialize;
 use settings::Settings;
 
+use crate::{
+    include::{references, resolve_checked},
+    Episodes, MediaKind,
+};
+
 pub trait Episode {
     fn get_season(&self) -> Option<u16>;
     fn get_episode(&self) -> Option<u16>;
+    /// 范围或列表选择的多集，写法见 [`Episodes`]
+    fn get_episodes(&self) -> Option<&Episodes>;
     fn merge_with(&mut self, other: &Self);
+
+    /// 是否适用于第 `season` 季第 `episode` 集，没有填写的条件都满足
+    fn matches(&self, season: u16, episode: u16) -> bool {
+        self.get_season().map_or(true, |x| x == season)
+            && self.get_episode().map_or(true, |x| x == episode)
+            && self.get_episodes().map_or(true, |x| x.contains(episode))
+    }
+
+    /// 具体程度，合并时越具体的配置越晚合并，覆盖前面的值
+    ///
+    /// 依次比较：是否指定季，指定单集 > 指定多集 > 不指定集，多集中集数越少越具体
+    fn specificity(&self) -> (bool, u8, Reverse<usize>) {
+        let (rank, len) = match (self.get_episode(), self.get_episodes()) {
+            (Some(_), _) => (2, 1),
+            (None, Some(x)) => (1, x.len()),
+            (None, None) => (0, usize::MAX),
+        };
+        (self.get_season().is_some(), rank, Reverse(len))
+    }
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
 #[allow(unused)]
+#[serde(deny_unknown_fields)]
 pub struct MarkSettings {
     // 多媒体目录
     pub id: String,
     pub title: String,
     pub path: Option<PathBuf>,
-    pub parts: Option<Vec<String>>,
+    pub parts: Option<Vec<MarkPart>>,
     pub suffix_parts: Option<Vec<String>>,
     pub with_suffix: Option<bool>,
-    pub exclude_segments: Option<Vec<(u64, u64)>>,
-    pub include_segments: Option<Vec<(u64, u64)>>,
+    pub exclude_segments: Option<Vec<Segment>>,
+    pub include_segments: Option<Vec<Segment>>,
     pub trans_1080p: Option<bool>,
+    // 转码配置名称
+    pub profile: Option<String>,
+}
+
+/// 拼接视频使用的片段，可以是片段库中的 ID 或文件路径
+///
+/// 在 TOML 中可以直接写 ID，需要截取时写作表
+///
+/// ```toml
+/// parts = ["爱2.14.3", { id = "爱2.14.4", segment = [10, "00:01:35"] }]
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(from = "MarkPartValue")]
+pub struct MarkPart {
+    pub id: String,
+    /// 只使用这段，`None` 时使用整个片段
+    pub segment: Option<Segment>,
+}
+
+impl From<&str> for MarkPart {
+    fn from(id: &str) -> Self {
+        Self { id: id.to_string(), segment: None }
+    }
+}
+
+#[derive(Deserialize, JsonSchema)]
+#[serde(untagged, deny_unknown_fields)]
+enum MarkPartValue {
+    Id(String),
+    Table { id: String, segment: Option<Segment> },
+}
+
+impl JsonSchema for MarkPart {
+    fn schema_name() -> String {
+        "MarkPart".to_string()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        MarkPartValue::json_schema(gen)
+    }
+}
+
+impl From<MarkPartValue> for MarkPart {
+    fn from(value: MarkPartValue) -> Self {
+        match value {
+            MarkPartValue::Id(id) => Self { id, segment: None },
+            MarkPartValue::Table { id, segment } => Self { id, segment },
+        }
+    }
 }
 
 impl MarkSettings {
@@ -40,18 +121,67 @@ impl MarkSettings {
             None => false,
         }
     }
+
+    /// 只保留的片段，设置了 `include_segments` 时再去掉其中的 `exclude_segments`
+    ///
+    /// Examples
+    ///
+    /// ```
+    /// use bili_video::Segment;
+    /// use media::MediaSettings;
+    ///
+    /// let media = MediaSettings::from_path("examples/media.toml").unwrap();
+    /// let mark = media.get_mark("2-14-1").unwrap();
+    /// assert_eq!(mark.keep_segments(), None);
+    ///
+    /// let mut mark = media.get_mark("path").unwrap();
+    /// mark.include_segments = Some(vec![Segment::new(300.0, 400.0), Segment::new(0.0, 90.0)]);
+    /// mark.exclude_segments = Some(vec![Segment::new(0.0, 30.0)]);
+    /// assert_eq!(mark.keep_segments(), Some(vec![
+    ///     Segment::new(300.0, 400.0),
+    ///     Segment::new(30.0, 90.0),
+    /// ]));
+    /// ```
+    pub fn keep_segments(&self) -> Option<Vec<Segment>> {
+        let include = self.include_segments.as_ref()?;
+        let exclude = self.exclude_segments.clone().unwrap_or_default();
+        Some(include.iter().flat_map(|x| x.subtract(&exclude)).collect())
+    }
+
+    /// 转码使用的配置名称，设置了 `trans_1080p` 时默认为 `1080p`
+    pub fn profile(&self) -> Option<String> {
+        match &self.profile {
+            Some(p) => Some(p.clone()),
+            None if self.trans_1080p() => Some("1080p".to_string()),
+            None => None,
+        }
+    }
 }
 
-#[derive(Debug, Clone, Deserialize, Default)]
+#[derive(Debug, Clone, Deserialize, Default, JsonSchema)]
 #[allow(unused)]
+#[serde(deny_unknown_fields)]
 pub struct SpliterSettings {
     // 多媒体目录
     pub season: Option<u16>,
     pub episode: Option<u16>,
+    pub episodes: Option<Episodes>,
     pub count: Option<usize>,
     pub suffix_parts: Option<Vec<String>>,
     pub screenshot_seconds: Option<Vec<u64>>,
-    pub exclude_segments: Option<Vec<(u64, u64)>>,
+    pub exclude_segments: Option<Vec<Segment>>,
+    // 每一部分的最大大小（MB）
+    pub max_size: Option<f64>,
+    // 两遍编码到最大大小时使用的转码配置
+    pub size_profile: Option<String>,
+    // 分割点对齐方式：fixed、scene、silence
+    pub boundary: Option<BoundaryMode>,
+    // 在理想分割点前后多少秒内查找
+    pub boundary_window: Option<f64>,
+    // 每一部分的最大时长
+    pub max_duration: Option<Timestamp>,
+    // 指定的分割时间点
+    pub split_points: Option<Vec<Timestamp>>,
 }
 
 impl SpliterSettings {
@@ -59,6 +189,35 @@ impl SpliterSettings {
         self.screenshot_seconds.clone().unwrap_or(vec![10, 20, 30, 300, 400, 500])
     }
 
+    /// 分割方式，优先使用 `split_points`，其次 `max_duration`，最后 `count`
+    ///
+    /// 合并多层配置时，更具体的一层设置了任意一种方式就会替换其他方式
+    ///
+    /// Examples
+    ///
+    /// ```
+    /// use bili_video::{SplitBy, Timestamp};
+    /// use media::SpliterSettings;
+    ///
+    /// let mut spliter = SpliterSettings { count: Some(3), ..Default::default() };
+    /// assert_eq!(spliter.split_by(), Some(SplitBy::Parts(3)));
+    ///
+    /// spliter.max_duration = Some(Timestamp(480.0));
+    /// assert_eq!(spliter.split_by(), Some(SplitBy::MaxDuration(480.0)));
+    ///
+    /// spliter.split_points = Some(vec![Timestamp(600.0)]);
+    /// assert_eq!(spliter.split_by(), Some(SplitBy::Timestamps(vec![600.0])));
+    /// ```
+    pub fn split_by(&self) -> Option<SplitBy> {
+        if let Some(points) = &self.split_points {
+            return Some(SplitBy::Timestamps(points.iter().map(|x| x.0).collect()));
+        }
+        if let Some(duration) = self.max_duration {
+            return Some(SplitBy::MaxDuration(duration.0));
+        }
+        self.count.map(SplitBy::Parts)
+    }
+
 }
 
 impl Episode for SpliterSettings {
@@ -67,6 +226,10 @@ impl Episode for SpliterSettings {
         self.episode
     }
 
+    fn get_episodes(&self) -> Option<&Episodes> {
+        self.episodes.as_ref()
+    }
+
     fn get_season(&self) -> Option<u16> {
         self.season
     }
@@ -78,8 +241,11 @@ impl Episode for SpliterSettings {
         if other.episode.is_some() {
             self.episode = other.episode;
         }
-        if other.count.is_some() {
+        // 分割方式只能有一种，更具体的配置设置任意一种时替换之前的方式
+        if other.count.is_some() || other.max_duration.is_some() || other.split_points.is_some() {
             self.count = other.count;
+            self.max_duration = other.max_duration;
+            self.split_points = other.split_points.clone();
         }
         if other.suffix_parts.is_some() {
             self.suffix_parts = other.suffix_parts.clone();
@@ -90,16 +256,30 @@ impl Episode for SpliterSettings {
         if other.exclude_segments.is_some() {
             self.exclude_segments = other.exclude_segments.clone();
         }
+        if other.max_size.is_some() {
+            self.max_size = other.max_size;
+        }
+        if other.size_profile.is_some() {
+            self.size_profile = other.size_profile.clone();
+        }
+        if other.boundary.is_some() {
+            self.boundary = other.boundary;
+        }
+        if other.boundary_window.is_some() {
+            self.boundary_window = other.boundary_window;
+        }
     }
 }
 
-#[derive(Debug, Clone, Deserialize, Default)]
+#[derive(Debug, Clone, Deserialize, Default, JsonSchema)]
 #[allow(unused)]
+#[serde(deny_unknown_fields)]
 pub struct TransSettings {
     // 多媒体目录
     pub season: Option<u16>,
     pub episode: Option<u16>,
-    pub exclude_segments: Option<Vec<(u64, u64)>>,
+    pub episodes: Option<Episodes>,
+    pub exclude_segments: Option<Vec<Segment>>,
 }
 
 impl Episode for TransSettings {
@@ -111,6 +291,10 @@ impl Episode for TransSettings {
         self.episode
     }
 
+    fn get_episodes(&self) -> Option<&Episodes> {
+        self.episodes.as_ref()
+    }
+
     fn merge_with(&mut self, other: &TransSettings) {
         if other.season.is_some() {
             self.season = other.season;
@@ -124,15 +308,17 @@ impl Episode for TransSettings {
     }
 }
 
-#[derive(Debug, Clone, Deserialize, Default)]
+#[derive(Debug, Clone, Deserialize, Default, JsonSchema)]
 #[allow(unused)]
+#[serde(deny_unknown_fields)]
 pub struct EpisodeSettings {
     // 多媒体目录
     pub season: Option<u16>,
     pub episode: Option<u16>,
+    pub episodes: Option<Episodes>,
     pub title: Option<String>,
     pub tag: Option<String>,
-    pub exclude_segments: Option<Vec<(u64, u64)>>,
+    pub exclude_segments: Option<Vec<Segment>>,
 }
 
 impl Episode for EpisodeSettings {
@@ -145,6 +331,10 @@ impl Episode for EpisodeSettings {
         self.episode
     }
 
+    fn get_episodes(&self) -> Option<&Episodes> {
+        self.episodes.as_ref()
+    }
+
     fn merge_with(&mut self, other: &Self) {
         if other.season.is_some() {
             self.season = other.season;
@@ -164,12 +354,14 @@ impl Episode for EpisodeSettings {
     }
 }
 
-#[derive(Debug, Clone, Deserialize, Default)]
+#[derive(Debug, Clone, Deserialize, Default, JsonSchema)]
 #[allow(unused)]
+#[serde(deny_unknown_fields)]
 pub struct UploaderSettings {
     // 多媒体目录
     pub season: Option<u16>,
     pub episode: Option<u16>,
+    pub episodes: Option<Episodes>,
     pub dtime: Option<String>,
     pub tag: Option<String>,
 }
@@ -184,6 +376,10 @@ impl Episode for UploaderSettings {
         self.episode
     }
 
+    fn get_episodes(&self) -> Option<&Episodes> {
+        self.episodes.as_ref()
+    }
+
     fn merge_with(&mut self, other: &Self) {
         if other.season.is_some() {
             self.season = other.season;
@@ -201,11 +397,18 @@ impl Episode for UploaderSettings {
 }
 
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 #[allow(unused)]
+#[serde(deny_unknown_fields)]
 pub struct MediaSettings {
+    // 继承和引用的配置，读取时已经合并
+    pub extends: Option<String>,
+    pub include: Option<Vec<String>>,
+
     pub name: String,
     pub title: String,
+    // 媒体类型，默认是电视剧
+    pub kind: Option<MediaKind>,
     pub media_dir: Option<String>,
     pub suffix_parts: Option<Vec<String>>,
 
@@ -224,28 +427,161 @@ pub struct MediaSettings {
     // 制作配置
     pub marks: Option<Vec<MarkSettings>>,
 
+    // 转码配置
+    pub profiles: Option<Vec<ProfilePatch>>,
+
     // 配置
     #[serde(skip)]
     pub settings: Option<Settings>,
 }
 
+/// 合并前的单个配置文件，所有字段都可以不填，只用来检查字段
+///
+/// `marks` 可以只覆盖部分字段，合并后再检查
+#[derive(Deserialize)]
+#[allow(unused)]
+#[serde(deny_unknown_fields)]
+struct MediaPatch {
+    extends: Option<String>,
+    // 可以是单个文件名
+    include: Option<toml::Value>,
+    name: Option<String>,
+    title: Option<String>,
+    kind: Option<MediaKind>,
+    media_dir: Option<String>,
+    suffix_parts: Option<Vec<String>>,
+    episodes: Option<Vec<EpisodeSettings>>,
+    trans: Option<Vec<TransSettings>>,
+    uploaders: Option<Vec<UploaderSettings>>,
+    spliters: Option<Vec<SpliterSettings>>,
+    marks: Option<Vec<toml::Table>>,
+    profiles: Option<Vec<ProfilePatch>>,
+}
+
 impl MediaSettings {
     pub fn new(name: &str) -> Result<Self> {
-        let path = Settings::media().join(format!("{}.toml", name));
-        Self::from_path(path)
+        Self::from_path(Self::path(name))
+    }
+
+    /// 媒体配置文件的位置
+    pub fn path(name: &str) -> PathBuf {
+        Settings::media().join(format!("{}.toml", name))
     }
 
+    /// 读取配置，片段无效时返回错误，乱序和重叠的片段在使用时自动修正
     pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
-        let c = Settings::build_config(path)?;
-        let mut s: Self = c.try_deserialize()?;
+        let s = Self::read(&path)?;
+        if let Some((place, e)) = s.check_segments().into_iter().find(|(_, e)| !e.is_fixable()) {
+            return Err(anyhow!("{:?} {}: {}", path.as_ref(), place, e));
+        }
+        Ok(s)
+    }
+
+    /// 读取配置，不检查片段
+    ///
+    /// 未知的字段会被拒绝，错误中包含所在的行和列
+    ///
+    /// 设置了 `extends` 或 `include` 时先合并引用的文件
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let s = if references(&path)?.is_empty() {
+            Self::parse(&fs::read_to_string(&path)?)
+        } else {
+            Self::resolve(&path)
+        };
+        let mut s = s.map_err(|e| anyhow!("{:?}: {}", path.as_ref(), e))?;
         s.settings = Some(Settings::new()?);
         Ok(s)
     }
 
+    /// 合并 `extends` 和 `include` 后的配置
+    ///
+    /// 合并后的配置没有行号，合并前先检查每个文件中的字段，错误中带有所在文件和行号
+    pub fn resolve<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let check = |text: &str| -> Result<()> {
+            toml::from_str::<MediaPatch>(text)?;
+            Ok(())
+        };
+        Ok(toml::Value::Table(resolve_checked(path, &check)?).try_into()?)
+    }
+
+    /// 解析配置内容，不加载 `bilibili.toml`
+    ///
+    /// Examples
+    ///
+    /// ```
+    /// use media::MediaSettings;
+    ///
+    /// let err = MediaSettings::parse("name = \"a\"\ntitle = \"b\"\n\n[[spliters]]\nremove_parts = [[0, 90]]\n").unwrap_err();
+    /// let message = err.to_string();
+    /// assert!(message.contains("line 5"));
+    /// assert!(message.contains("remove_parts"));
+    /// ```
+    pub fn parse(text: &str) -> Result<Self> {
+        Ok(toml::from_str(text)?)
+    }
+
+    /// 检查所有 `exclude_segments` 和 `include_segments`，返回问题所在的位置和问题
+    ///
+    /// `include_segments` 按给定顺序合并，不检查乱序和重叠
+    ///
+    /// Examples
+    ///
+    /// ```
+    /// use media::MediaSettings;
+    ///
+    /// let media = MediaSettings::from_path("examples/media.toml").unwrap();
+    /// assert!(media.check_segments().is_empty());
+    /// ```
+    pub fn check_segments(&self) -> Vec<(String, SegmentError)> {
+        let mut problems = Vec::new();
+        let mut check = |place: String, segments: &Option<Vec<Segment>>| {
+            if let Some(segments) = segments {
+                problems.extend(check_segments(segments).into_iter().map(|e| (place.clone(), e)));
+            }
+        };
+        for x in self.episodes.iter().flatten() {
+            check(place("episodes", x), &x.exclude_segments);
+        }
+        for x in self.trans.iter().flatten() {
+            check(place("trans", x), &x.exclude_segments);
+        }
+        for x in self.spliters.iter().flatten() {
+            check(place("spliters", x), &x.exclude_segments);
+        }
+        for x in self.marks.iter().flatten() {
+            check(format!("marks {}", x.id), &x.exclude_segments);
+        }
+        for x in self.marks.iter().flatten() {
+            let include = x.include_segments.iter().flatten().filter_map(|x| x.validate().err());
+            problems.extend(include.map(|e| (format!("marks {} include_segments", x.id), e)));
+            let parts = x.parts.iter().flatten().filter_map(|x| x.segment?.validate().err());
+            problems.extend(parts.map(|e| (format!("marks {} parts", x.id), e)));
+        }
+        problems
+    }
+
     pub fn settings(&self) -> &Settings {
         self.settings.as_ref().expect("Failed get settings")
     }
 
+    /// 媒体类型，没有填写时季数大于 1000 的视为电影，其他视为电视剧
+    ///
+    /// Examples
+    ///
+    /// ```
+    /// use media::{MediaKind, MediaSettings};
+    ///
+    /// let media = MediaSettings::parse("name = \"a\"\ntitle = \"b\"\n").unwrap();
+    /// assert_eq!(media.kind(1), MediaKind::Series);
+    /// assert_eq!(media.kind(2009), MediaKind::Movie);
+    ///
+    /// let media = MediaSettings::parse("name = \"a\"\ntitle = \"b\"\nkind = \"variety\"\n").unwrap();
+    /// assert_eq!(media.kind(2024), MediaKind::Variety);
+    /// ```
+    pub fn kind(&self, season: u16) -> MediaKind {
+        self.kind.or(MediaKind::guess(season)).unwrap_or_default()
+    }
+
     /// 获取媒体存储位置
     pub fn media_dir(&self) -> PathBuf {
         if let Some(_dir) = &self.media_dir {
@@ -267,6 +603,10 @@ impl MediaSettings {
     /// assert_eq!(uploader.tag, Some("电视剧,影视剪辑,龙门镖局1.5,龙门镖局".to_string()));
     /// assert_eq!(uploader.dtime, Some("2025-01-19 11:00:00".to_string()));
     ///
+    /// // 列表选择的多集，第 6 集单独填写的 dtime 优先
+    /// let uploader = media.get_uploader(3, 5).unwrap();
+    /// assert_eq!(uploader.dtime, Some("2025-01-18 11:00:00".to_string()));
+    ///
     /// let uploader = media.get_uploader(3, 7).unwrap();
     /// assert_eq!(uploader.tag, Some("电视剧,影视剪辑,龙门镖局1.5,龙门镖局".to_string()));
     /// assert_eq!(uploader.dtime, None);
@@ -284,25 +624,26 @@ impl MediaSettings {
     /// Examples
     ///
     /// ```
+    /// use bili_video::{Segment, SplitBy};
     /// use media::MediaSettings;
     ///
     /// let media = MediaSettings::from_path("examples/media.toml").unwrap();
     ///
     /// let spliter = media.get_spliter(3, 12).unwrap();
-    /// assert_eq!(spliter.remove_parts, Some(vec![(0, 90)]));
+    /// assert_eq!(spliter.exclude_segments, Some(vec![Segment::new(0.0, 90.0)]));
     /// assert_eq!(spliter.screenshot_seconds, Some(vec![10, 20, 30]));
     /// assert_eq!(spliter.suffix_parts, Some(vec!["ipartment".to_string()]));
     /// assert_eq!(spliter.count, Some(3));
     ///
     /// let spliter = media.get_spliter(3, 11).unwrap();
     /// assert_eq!(spliter.count, Some(2));
-    /// assert_eq!(spliter.remove_parts, Some(vec![(0, 80)]));
+    /// assert_eq!(spliter.exclude_segments, Some(vec![Segment::new(0.0, 80.0)]));
     ///
     /// let spliter = media.get_spliter(4, 11).unwrap();
     /// assert_eq!(spliter.count, Some(5));
     ///
     /// let spliter = media.get_spliter(2009, 1201).unwrap();
-    /// assert_eq!(spliter.tag, Some("电影,喜剧,多线索叙事,黄渤,九孔,戎祥,高捷,王双宝,巴多,王迅".to_string()));
+    /// assert_eq!(spliter.split_by(), Some(SplitBy::Parts(5)));
     /// ```
     pub fn get_spliter(&self, season: u16, episode: u16) -> Option<SpliterSettings> {
         self.get_episode_settings(season, episode, &None, &self.spliters)
@@ -313,13 +654,17 @@ impl MediaSettings {
     /// Examples
     ///
     /// ```
-    /// use media::MediaSettings;
+    /// use bili_video::{BoundaryMode, Segment, SplitBy, Timestamp, TranscodeProfile};
+    /// use media::{MarkPart, MediaSettings};
     /// use std::path::PathBuf;
     ///
     /// let media = MediaSettings::from_path("examples/media.toml").unwrap();
     ///
     /// let item = media.get_mark("2-14-1").unwrap();
-    /// assert_eq!(item.parts, Some(vec!["爱2.14.3".to_string(), "爱2.14.4".to_string()]));
+    /// assert_eq!(item.parts, Some(vec![
+    ///     MarkPart::from("爱2.14.3"),
+    ///     MarkPart { id: "爱2.14.4".to_string(), segment: Some(Segment::new(10.0, 95.0)) },
+    /// ]));
     /// assert_eq!(item.suffix_parts, Some(vec!["ipartment".to_string()]));
     ///
     /// let item = media.get_mark("2-14-2").unwrap();
@@ -331,8 +676,9 @@ impl MediaSettings {
     /// assert_eq!(item.path, Some(PathBuf::from("examples/data/trailer.mp4")));
     /// assert!(!item.with_suffix());
     /// assert!(item.trans_1080p());
-    /// assert_eq!(item.include_segments, Some(vec![(0, 90)]));
-    /// assert_eq!(item.exclude_segments, Some(vec![(0, 90)]));
+    /// assert_eq!(item.profile(), Some("1080p".to_string()));
+    /// assert_eq!(item.include_segments, Some(vec![Segment::new(0.0, 90.0)]));
+    /// assert_eq!(item.exclude_segments, Some(vec![Segment::new(0.0, 90.0)]));
     /// ```
     pub fn get_mark(&self, id: &str) -> Option<MarkSettings> {
         if let Some(marks) = &self.marks {
@@ -347,6 +693,45 @@ impl MediaSettings {
         None
     }
 
+    /// 片段的位置，先在片段库中查找，找不到时作为文件路径
+    pub fn get_part_path(&self, id: &str) -> Result<PathBuf> {
+        let path = self.settings().part.get_path(&self.name, id);
+        if path.exists() {
+            return Ok(path);
+        }
+        let path = PathBuf::from(id);
+        if path.exists() {
+            return Ok(path);
+        }
+        Err(anyhow!("片段 {} 不存在", id))
+    }
+
+    /// 获取转码配置，媒体配置覆盖 `bilibili.toml`，`bilibili.toml` 覆盖内置配置，
+    /// 每一层只替换填写的字段
+    ///
+    /// Examples
+    ///
+    /// ```
+    /// use media::MediaSettings;
+    ///
+    /// let media = MediaSettings::from_path("examples/media.toml").unwrap();
+    ///
+    /// let profile = media.get_profile("1080p").unwrap();
+    /// assert_eq!(profile.crf, 20);
+    /// assert_eq!(profile.width, 1920);
+    ///
+    /// // 只覆盖 crf，分辨率仍然是 720p
+    /// let profile = media.get_profile("720p").unwrap();
+    /// assert_eq!(profile.crf, 21);
+    /// assert_eq!((profile.width, profile.height), (1280, 720));
+    ///
+    /// assert!(media.get_profile("8k").is_none());
+    /// ```
+    pub fn get_profile(&self, name: &str) -> Option<TranscodeProfile> {
+        let profiles = self.profiles.clone().unwrap_or_default();
+        resolve_profile(name, &[&self.settings().profiles, &profiles])
+    }
+
     /// 获取转码视频配置
     ///
     /// ```
@@ -357,17 +742,34 @@ impl MediaSettings {
     /// 获取视频配置
     ///
     /// ```
+    /// use bili_video::{BoundaryMode, Segment, SplitBy, Timestamp, TranscodeProfile};
     /// use media::MediaSettings;
     ///
     /// let media = MediaSettings::from_path("examples/media.toml").unwrap();
     ///
     /// let episode = media.get_episode(1, 2).unwrap();
     /// assert_eq!(episode.title, Some("标题".to_string()));
+    /// assert_eq!(episode.exclude_segments, Some(vec![Segment::new(0.0, 89.4)]));
+    ///
+    /// let episode = media.get_episode(2009, 1201).unwrap();
+    /// assert_eq!(episode.exclude_segments, Some(vec![Segment::new(0.0, 36.0)]));
     /// ```
     pub fn get_episode(&self, season: u16, episode: u16) -> Option<EpisodeSettings> {
         self.get_episode_settings(season, episode, &None, &self.episodes)
     }
 
+    /// 合并适用于第 `season` 季第 `episode` 集的所有配置
+    ///
+    /// 按具体程度从低到高合并，后合并的覆盖前面的值：
+    ///
+    /// 1. `stg`
+    /// 2. 不指定季和集的默认配置
+    /// 3. 只指定集（`episode` 或 `episodes`），适用于所有季
+    /// 4. 只指定季
+    /// 5. 指定季和多集，集数越少越具体
+    /// 6. 指定季和单集
+    ///
+    /// 具体程度相同时文件中靠前的配置优先
     pub fn get_episode_settings<T: Episode + Clone + Default>(
         &self,
         season: u16,
@@ -384,25 +786,56 @@ impl MediaSettings {
         }
 
         if let Some(configs) = settings {
-            // 查找默认
-            if let Some(ep) = configs.iter()
-                .find(|x| x.get_season().is_none() && x.get_episode().is_none()) {
-                item.merge_with(ep);
-                has = true;
-            }
-            // 查找每季
-            if let Some(ep) = configs.iter()
-                .find(|x| x.get_season() == Some(season) && x.get_episode().is_none()) {
-                item.merge_with(ep);
+            let mut matched: Vec<(usize, &T)> = configs
+                .iter()
+                .enumerate()
+                .filter(|(_, x)| x.matches(season, episode))
+                .collect();
+            matched.sort_by_key(|(index, x)| (x.specificity(), Reverse(*index)));
+            for (_, config) in matched {
+                item.merge_with(config);
                 has = true;
             }
-            // 查找每集
-            if let Some(ep) = configs.iter()
-                .find(|x| x.get_season() == Some(season) && x.get_episode() == Some(episode)) {
-                item.merge_with(ep);
-            has = true;
-            }
         }
         if has { Some(item) } else { None }
     }
 }
+
+/// 问题所在的位置，如 `episodes S01E02`、`spliters S03 E1-12`
+pub(crate) fn place<T: Episode>(kind: &str, x: &T) -> String {
+    let mut place = match (x.get_season(), x.get_episode()) {
+        (Some(s), Some(e)) => format!("{} S{:02}E{:02}", kind, s, e),
+        (Some(s), None) => format!("{} S{:02}", kind, s),
+        (None, Some(e)) => format!("{} E{:02}", kind, e),
+        (None, None) => kind.to_string(),
+    };
+    if let Some(episodes) = x.get_episodes() {
+        place.push_str(&format!(" E{}", episodes));
+    }
+    place
+}
+
+#[cfg(test)]
+mod tests {
+    use bili_video::{Segment, SplitBy};
+
+    use super::MediaSettings;
+
+    #[test]
+    fn test_spliter_precedence() {
+        let media = MediaSettings::from_path("examples/media.toml").unwrap();
+
+        // 范围选择的多集优先于只指定季
+        let spliter = media.get_spliter(3, 13).unwrap();
+        assert_eq!(spliter.count, Some(4));
+        assert_eq!(spliter.exclude_segments, Some(vec![Segment::new(0.0, 75.0)]));
+
+        // 整季的 max_duration 替换默认的 count
+        let spliter = media.get_spliter(2020, 1).unwrap();
+        assert_eq!(spliter.split_by(), Some(SplitBy::MaxDuration(480.0)));
+
+        // 更具体的配置设置了 count，替换整季的 max_duration
+        let spliter = media.get_spliter(2020, 6211).unwrap();
+        assert_eq!(spliter.split_by(), Some(SplitBy::Parts(3)));
+    }
+}
@@ -2,7 +2,7 @@ use std::{fs::{self, File}, path::{Path, PathBuf}};
 use rand::seq::SliceRandom;
 
 use anyhow::{anyhow, Result};
-use bili_video::Video;
+use bili_video::{normalize_loudness, LoudnessError, Loudnorm, TranscodeProfile, Video};
 use serde::{Deserialize, Serialize};
 use settings::Settings;
 
@@ -18,8 +18,16 @@ impl Part {
     }
 }
 
-pub fn init_part() -> Result<()> {
+/// 扫描片段库生成 `part.json`
+///
+/// `normalize` 为 true 时，响度和目标相差过大的片段会被标准化并替换原文件
+pub fn init_part(normalize: bool) -> Result<()> {
     let stg = Settings::new()?;
+    // 标准化时音频按片段库的转码配置重新编码
+    let profile = match normalize {
+        true => Some(stg.get_part_profile().ok_or(anyhow!("没有找到片段库的转码配置 {:?}", stg.part.profile))?),
+        false => None,
+    };
     let mut parts: Vec<Part> = Vec::new();
     for part_name in &stg.part.names{
         let dirname = stg.part.home().join(part_name);
@@ -28,8 +36,8 @@ pub fn init_part() -> Result<()> {
         for entry in fs::read_dir(dir)? {
             let entry = entry?;
             let filename = entry.file_name().into_string().unwrap();
-            // 过滤文件类型
-            if !filename.ends_with("ts") {
+            // 过滤文件类型，跳过上次中断留下的临时文件
+            if !filename.ends_with(".ts") || filename.ends_with(".loudnorm.ts") {
                 continue;
             }
 
@@ -38,6 +46,12 @@ pub fn init_part() -> Result<()> {
             if video.duration > 180.0 {
                 continue;
             }
+            let video = match &profile {
+                Some(profile) if !video.audios.is_empty() => {
+                    normalize_part(&entry.path(), &stg.part.loudnorm, profile)?.unwrap_or(video)
+                }
+                _ => video,
+            };
             println!("{} => {:?}", part_name, &video);
             videos.push(video);
         }
@@ -50,6 +64,34 @@ pub fn init_part() -> Result<()> {
     Ok(())
 }
 
+/// 片段响度和目标相差过大时标准化并替换原文件，返回新的视频信息
+///
+/// 音频按片段库转码配置 `profile` 的参数重新编码
+fn normalize_part(path: &Path, loudnorm: &Loudnorm, profile: &TranscodeProfile) -> Result<Option<Video>> {
+    let temp = path.with_extension("loudnorm.ts");
+    match normalize_loudness(path, &temp, loudnorm, profile) {
+        Ok(true) => {
+            fs::rename(&temp, path)?;
+            println!("{:?} 响度已标准化到 {} LUFS", path, loudnorm.i);
+            Ok(Some(Video::from(path)?))
+        }
+        Ok(false) => Ok(None),
+        Err(e) => {
+            if temp.exists() {
+                let _ = fs::remove_file(&temp);
+            }
+            // 没有音频或静音的片段保持原样，取消和超时等错误直接返回
+            match e.downcast_ref::<LoudnessError>() {
+                Some(reason) => {
+                    println!("{:?} 跳过响度标准化: {}", path, reason);
+                    Ok(None)
+                }
+                None => Err(e),
+            }
+        }
+    }
+}
+
 pub fn get_rand_part_path(names: Vec<String>) -> Result<PathBuf> {
     let json_str = fs::read_to_string(Settings::part())?;
     let parts: Vec<Part> = serde_json::from_str(&json_str)?;
@@ -1,14 +1,25 @@
 use std::{env, path::{Path, PathBuf}};
 
+use bili_video::{resolve_profile, Loudnorm, ProfilePatch, TranscodeProfile};
 use config::{Config, ConfigError, Environment, File};
 use lazytool::RegexParser;
+use schemars::{
+    gen::SchemaGenerator,
+    schema::{ArrayValidation, InstanceType, Schema, SchemaObject},
+    JsonSchema,
+};
 use serde::Deserialize;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 #[allow(unused)]
 pub struct Part {
     pub home: String,
     pub names: Vec<String>,
+    // `init part --normalize` 时的响度标准化参数
+    #[serde(default)]
+    pub loudnorm: Loudnorm,
+    // 片段库的转码配置名称，标准化时使用其中的音频参数，默认 1080p
+    pub profile: Option<String>,
 }
 
 impl Part {
@@ -21,14 +32,14 @@ impl Part {
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 #[allow(unused)]
 pub struct App {
     // 多媒体目录
     pub media_dir: PathBuf,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 #[allow(unused)]
 pub struct Up {
     // 多媒体目录
@@ -44,21 +55,25 @@ impl Up {
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 #[allow(unused)]
 pub struct Media {
     pub name: String,
     pub title: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 #[allow(unused)]
 pub struct Settings {
     pub app: App,
     pub part: Part,
     pub up: Vec<Up>,
+    #[schemars(schema_with = "regex_parsers_schema")]
     pub episode_regexs: Vec<RegexParser>,
     pub medias: Vec<Media>,
+    // 转码配置，覆盖内置的同名配置
+    #[serde(default)]
+    pub profiles: Vec<ProfilePatch>,
 }
 
 impl Settings {
@@ -127,6 +142,16 @@ impl Settings {
     pub fn get_media_by_title(&self, title: &str) -> Option<&Media> {
         self.medias.iter().find(|x| x.title == title)
     }
+
+    /// 获取转码配置，`bilibili.toml` 中的配置覆盖内置配置
+    pub fn get_profile(&self, name: &str) -> Option<TranscodeProfile> {
+        resolve_profile(name, &[&self.profiles])
+    }
+
+    /// 片段库使用的转码配置，没有设置时为 `1080p`
+    pub fn get_part_profile(&self) -> Option<TranscodeProfile> {
+        self.get_profile(self.part.profile.as_deref().unwrap_or("1080p"))
+    }
 }
 
 #[cfg(test)]
@@ -136,3 +161,14 @@ mod tests {
         todo!();
     }
 }
+
+/// `RegexParser` 来自 lazytool，只约束为表的数组
+fn regex_parsers_schema(_: &mut SchemaGenerator) -> Schema {
+    let item = SchemaObject { instance_type: Some(InstanceType::Object.into()), ..Default::default() };
+    SchemaObject {
+        instance_type: Some(InstanceType::Array.into()),
+        array: Some(Box::new(ArrayValidation { items: Some(Schema::from(item).into()), ..Default::default() })),
+        ..Default::default()
+    }
+    .into()
+}
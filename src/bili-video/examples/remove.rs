@@ -1,15 +1,15 @@
-use bili_video::Remover;
+use bili_video::{Remover, Segment};
 
 fn main() {
-    let r = Remover::new("examples/data/trailer.mp4", vec![(40, 60)]);
+    let r = Remover::new("examples/data/trailer.mp4", vec![Segment::new(40.0, 60.0)]);
     let to = format!("~/Downloads/1-{}.mp4", lazytool::current_timestamp());
     r.output(lazytool::expand_user(to)).unwrap();
 
-    let r = Remover::new("examples/data/trailer.mp4", vec![(0, 20), (40, 60)]);
+    let r = Remover::new("examples/data/trailer.mp4", vec![Segment::new(0.0, 20.0), Segment::new(40.0, 60.0)]);
     let to = format!("~/Downloads/2-{}.mp4", lazytool::current_timestamp());
     r.output(lazytool::expand_user(to)).unwrap();
 
-    let r = Remover::new("examples/data/trailer.mp4", vec![(10, 20), (40, 45)]);
+    let r = Remover::new("examples/data/trailer.mp4", vec![Segment::new(10.0, 20.5), Segment::new(40.0, 45.25)]);
     let to = format!("~/Downloads/2-{}.mp4", lazytool::current_timestamp());
     r.output(lazytool::expand_user(to)).unwrap();
 }
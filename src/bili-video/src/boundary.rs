@@ -0,0 +1,119 @@
+use std::{fmt, path::Path, str::FromStr};
+
+use anyhow::Result;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{detect::value_after, parse_silence, FfmpegCommand, Segment};
+
+/// 场景切换的阈值
+const SCENE_THRESHOLD: f64 = 0.3;
+
+/// 静音阈值（dB）和最短时长
+const SILENCE_NOISE: f64 = -35.0;
+const SILENCE_DURATION: f64 = 0.3;
+
+/// 分割点的选择方式
+///
+/// 在 TOML 中写作 `boundary = "scene"`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum BoundaryMode {
+    /// 等分，不调整
+    #[default]
+    Fixed,
+    /// 对齐到附近的场景切换
+    Scene,
+    /// 对齐到附近静音的中间
+    Silence,
+}
+
+impl FromStr for BoundaryMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fixed" => Ok(Self::Fixed),
+            "scene" => Ok(Self::Scene),
+            "silence" => Ok(Self::Silence),
+            _ => Err(format!("{} 不是有效的分割方式，可选 fixed、scene、silence", s)),
+        }
+    }
+}
+
+impl fmt::Display for BoundaryMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Fixed => write!(f, "fixed"),
+            Self::Scene => write!(f, "scene"),
+            Self::Silence => write!(f, "silence"),
+        }
+    }
+}
+
+/// 在 `ideal` 前后 `window` 秒内找到离它最近的场景切换或静音，没有时返回 `None`
+///
+/// Examples
+///
+/// ```ignore
+/// use bili_video::{find_boundary, BoundaryMode};
+///
+/// let time = find_boundary("/tmp/test.mp4", 300.0, 10.0, BoundaryMode::Scene).unwrap();
+/// println!("{:?}", time);
+/// ```
+pub fn find_boundary<P: AsRef<Path>>(path: P, ideal: f64, window: f64, mode: BoundaryMode) -> Result<Option<f64>> {
+    if mode == BoundaryMode::Fixed {
+        return Ok(None);
+    }
+    let range = Segment::new((ideal - window).max(0.0), ideal + window);
+    let null = if cfg!(windows) { "NUL" } else { "/dev/null" };
+
+    let mut cmd = FfmpegCommand::new();
+    cmd.input(&path).seek(range.start).duration(range.duration());
+    let output = cmd.output(null);
+    match mode {
+        BoundaryMode::Scene => output
+            .flag("-an")
+            .video_filter(&format!("select='gt(scene,{})',showinfo", SCENE_THRESHOLD)),
+        _ => output
+            .flag("-vn")
+            .audio_filter(&format!("silencedetect=noise={}dB:d={}", SILENCE_NOISE, SILENCE_DURATION)),
+    };
+    output.format("null");
+    let stderr = cmd.set_total(Some(range.duration())).analyze()?;
+
+    // 输入端 seek 后日志中的时间从 0 开始
+    let candidates: Vec<f64> = match mode {
+        BoundaryMode::Scene => parse_scene(&stderr),
+        _ => parse_silence(&stderr, range.duration())
+            .iter()
+            .map(|x| (x.start + x.end) / 2.0)
+            .collect(),
+    };
+    Ok(candidates
+        .into_iter()
+        .map(|x| x + range.start)
+        .filter(|x| (x - ideal).abs() <= window)
+        .min_by(|a, b| (a - ideal).abs().total_cmp(&(b - ideal).abs())))
+}
+
+/// 解析 `showinfo` 输出的场景切换时间
+///
+/// Examples
+///
+/// ```
+/// use bili_video::parse_scene;
+///
+/// let stderr = "\
+/// [Parsed_showinfo_1 @ 0x1] n:   0 pts: 93093 pts_time:7.5 duration: 1001
+/// [Parsed_showinfo_1 @ 0x1] n:   1 pts: 186186 pts_time:15.015 duration: 1001
+/// ";
+/// assert_eq!(parse_scene(stderr), vec![7.5, 15.015]);
+/// ```
+pub fn parse_scene(stderr: &str) -> Vec<f64> {
+    stderr
+        .lines()
+        .filter(|line| line.contains("Parsed_showinfo"))
+        .filter_map(|line| value_after(line, "pts_time:"))
+        .collect()
+}
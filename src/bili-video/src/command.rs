@@ -0,0 +1,273 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+
+use crate::{executor, format_timestamp, CancelToken};
+
+/// ffmpeg 命令构造器
+///
+/// 每个参数都是独立的 argv，不经过 shell，路径中包含空格也不会出错
+///
+/// Examples
+///
+/// ```
+/// use bili_video::FfmpegCommand;
+///
+/// let mut cmd = FfmpegCommand::new();
+/// cmd.input("/tmp/my video.mp4").seek(10.0).duration(20.0);
+/// cmd.output("/tmp/out.mp4").codec("copy");
+/// assert_eq!(cmd.args(), vec![
+///     "-ss", "00:00:10.000", "-t", "00:00:20.000", "-i", "/tmp/my video.mp4",
+///     "-c", "copy", "/tmp/out.mp4",
+/// ]);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct FfmpegCommand {
+    globals: Vec<String>,
+    inputs: Vec<Input>,
+    filter_complex: Option<String>,
+    outputs: Vec<Output>,
+    total: Option<f64>,
+    progress_offset: f64,
+    cancel_token: Option<CancelToken>,
+}
+
+impl FfmpegCommand {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 添加全局参数，放在所有输入之前
+    pub fn global(&mut self, flag: &str) -> &mut Self {
+        self.globals.push(flag.to_string());
+        self
+    }
+
+    /// 添加带值的全局参数
+    pub fn global_option(&mut self, key: &str, value: &str) -> &mut Self {
+        self.globals.push(key.to_string());
+        self.globals.push(value.to_string());
+        self
+    }
+
+    /// 覆盖已存在的输出文件
+    pub fn overwrite(&mut self) -> &mut Self {
+        self.global("-y")
+    }
+
+    /// 添加输入文件，返回该输入用于设置输入参数
+    pub fn input<P: AsRef<Path>>(&mut self, path: P) -> &mut Input {
+        self.inputs.push(Input::new(path));
+        self.inputs.last_mut().unwrap()
+    }
+
+    /// 设置 `-filter_complex` 滤镜图
+    pub fn filter_complex(&mut self, graph: &str) -> &mut Self {
+        self.filter_complex = Some(graph.to_string());
+        self
+    }
+
+    /// 添加输出文件，返回该输出用于设置输出参数
+    pub fn output<P: AsRef<Path>>(&mut self, path: P) -> &mut Output {
+        self.outputs.push(Output::new(path));
+        self.outputs.last_mut().unwrap()
+    }
+
+    /// 设置处理的媒体总时长，用于计算进度
+    pub fn set_total(&mut self, total: Option<f64>) -> &mut Self {
+        self.total = total;
+        self
+    }
+
+    pub fn total(&self) -> Option<f64> {
+        self.total
+    }
+
+    /// 设置进度中输出时间的起点，使用 `-copyts` 时为 seek 的位置
+    pub fn set_progress_offset(&mut self, offset: f64) -> &mut Self {
+        self.progress_offset = offset;
+        self
+    }
+
+    pub fn progress_offset(&self) -> f64 {
+        self.progress_offset
+    }
+
+    /// 只检查指定的取消令牌，默认为当前线程生效的令牌
+    pub fn set_cancel_token(&mut self, token: CancelToken) -> &mut Self {
+        self.cancel_token = Some(token);
+        self
+    }
+
+    pub fn cancel_token(&self) -> Option<CancelToken> {
+        self.cancel_token.clone()
+    }
+
+    /// 不包含 `ffmpeg` 的参数列表
+    pub fn args(&self) -> Vec<String> {
+        let mut args = self.globals.clone();
+        for input in &self.inputs {
+            args.extend(input.options.iter().cloned());
+            args.push("-i".to_string());
+            args.push(path_string(&input.path));
+        }
+        if let Some(graph) = &self.filter_complex {
+            args.push("-filter_complex".to_string());
+            args.push(graph.clone());
+        }
+        for output in &self.outputs {
+            args.extend(output.options.iter().cloned());
+            args.push(path_string(&output.path));
+        }
+        args
+    }
+
+    /// 完整的命令行参数
+    pub fn argv(&self) -> Vec<String> {
+        let mut argv = vec!["ffmpeg".to_string()];
+        argv.extend(self.args());
+        argv
+    }
+
+    /// 所有输入文件
+    pub fn inputs(&self) -> Vec<PathBuf> {
+        self.inputs.iter().map(|x| x.path.clone()).collect()
+    }
+
+    /// 所有输出文件
+    pub fn outputs(&self) -> Vec<PathBuf> {
+        self.outputs.iter().map(|x| x.path.clone()).collect()
+    }
+
+    /// 通过当前执行器执行命令
+    pub fn run(&self) -> Result<()> {
+        executor().run(self)
+    }
+
+    /// 通过当前执行器执行分析类命令，返回 ffmpeg 的日志输出
+    pub fn analyze(&self) -> Result<String> {
+        executor().analyze(self)
+    }
+}
+
+/// ffmpeg 输入文件及其参数
+#[derive(Debug, Clone)]
+pub struct Input {
+    path: PathBuf,
+    options: Vec<String>,
+}
+
+impl Input {
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self { path: path.as_ref().to_path_buf(), options: Vec::new() }
+    }
+
+    /// 添加参数
+    pub fn flag(&mut self, flag: &str) -> &mut Self {
+        self.options.push(flag.to_string());
+        self
+    }
+
+    /// 添加带值的参数
+    pub fn option(&mut self, key: &str, value: &str) -> &mut Self {
+        self.flag(key).flag(value)
+    }
+
+    /// 从指定时间开始读取（输入端 seek）
+    pub fn seek(&mut self, start: f64) -> &mut Self {
+        self.option("-ss", &format_timestamp(start))
+    }
+
+    /// 只读取指定时长
+    pub fn duration(&mut self, time: f64) -> &mut Self {
+        self.option("-t", &format_timestamp(time))
+    }
+
+    /// 指定输入格式
+    pub fn format(&mut self, format: &str) -> &mut Self {
+        self.option("-f", format)
+    }
+}
+
+/// ffmpeg 输出文件及其参数
+#[derive(Debug, Clone)]
+pub struct Output {
+    path: PathBuf,
+    options: Vec<String>,
+}
+
+impl Output {
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self { path: path.as_ref().to_path_buf(), options: Vec::new() }
+    }
+
+    /// 添加参数
+    pub fn flag(&mut self, flag: &str) -> &mut Self {
+        self.options.push(flag.to_string());
+        self
+    }
+
+    /// 添加带值的参数
+    pub fn option(&mut self, key: &str, value: &str) -> &mut Self {
+        self.flag(key).flag(value)
+    }
+
+    /// 选择输出的流，如 `0:v`、`a`
+    pub fn map(&mut self, spec: &str) -> &mut Self {
+        self.option("-map", spec)
+    }
+
+    /// 所有流的编码器
+    pub fn codec(&mut self, codec: &str) -> &mut Self {
+        self.option("-c", codec)
+    }
+
+    /// 视频编码器
+    pub fn video_codec(&mut self, codec: &str) -> &mut Self {
+        self.option("-c:v", codec)
+    }
+
+    /// 音频编码器
+    pub fn audio_codec(&mut self, codec: &str) -> &mut Self {
+        self.option("-c:a", codec)
+    }
+
+    /// 视频滤镜
+    pub fn video_filter(&mut self, filter: &str) -> &mut Self {
+        self.option("-vf", filter)
+    }
+
+    /// 音频滤镜
+    pub fn audio_filter(&mut self, filter: &str) -> &mut Self {
+        self.option("-af", filter)
+    }
+
+    /// 码流过滤器，`stream` 为 `v` 或 `a`
+    pub fn bsf(&mut self, stream: &str, filter: &str) -> &mut Self {
+        self.option(&format!("-bsf:{}", stream), filter)
+    }
+
+    /// 指定输出格式
+    pub fn format(&mut self, format: &str) -> &mut Self {
+        self.option("-f", format)
+    }
+
+    /// 从指定时间开始输出（输出端 seek）
+    pub fn seek(&mut self, start: f64) -> &mut Self {
+        self.option("-ss", &format_timestamp(start))
+    }
+
+    /// 只输出指定时长
+    pub fn duration(&mut self, time: f64) -> &mut Self {
+        self.option("-t", &format_timestamp(time))
+    }
+
+    /// 只输出指定帧数的视频
+    pub fn frames(&mut self, count: u64) -> &mut Self {
+        self.option("-frames:v", &count.to_string())
+    }
+}
+
+fn path_string(path: &Path) -> String {
+    path.to_string_lossy().into_owned()
+}
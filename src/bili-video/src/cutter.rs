@@ -0,0 +1,418 @@
+use std::{fs, path::{Path, PathBuf}};
+
+use anyhow::Result;
+
+use crate::{concat, cut, cut_quick, executor, snap_to_keyframe, to_ts, FfmpegCommand, Segment, Video};
+
+/// 截取方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CutMode {
+    /// 重新编码，按请求的时间精确截取
+    #[default]
+    Accurate,
+    /// 直接复制流，边界对齐到最近的关键帧
+    Quick,
+    /// 只重新编码开头和结尾不完整的 GOP，中间部分直接复制
+    Smart,
+}
+
+/// 单个片段的截取计划
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cut {
+    /// 请求截取的片段
+    pub requested: Segment,
+    /// 实际截取的片段
+    pub effective: Segment,
+    /// 实际使用的截取方式
+    pub mode: CutMode,
+    /// 智能模式下直接复制的中间部分，边界都在关键帧上
+    pub copy: Option<Segment>,
+}
+
+impl Cut {
+    /// 实际边界和请求边界的最大偏差
+    pub fn drift(&self) -> f64 {
+        let start = (self.effective.start - self.requested.start).abs();
+        let end = (self.effective.end - self.requested.end).abs();
+        start.max(end)
+    }
+
+    /// 按计划截取
+    pub fn run<F, T>(&self, from: F, to: T) -> Result<PathBuf>
+    where
+        F: AsRef<Path>,
+        T: AsRef<Path>,
+    {
+        let (from, to) = (from.as_ref(), to.as_ref());
+        match self.smart_options(from)? {
+            Some((copy, options)) => smart_cut(from, to, self.effective, copy, &options),
+            None => self.run_whole(from, to),
+        }
+    }
+
+    /// 按计划截取为可以直接拼接的 ts，`temps` 记录需要删除的中间文件
+    ///
+    /// 智能模式下各部分分别转为 ts 返回，由调用方和其他片段一起拼接，不用先合并再转一次
+    pub(crate) fn run_ts(&self, from: &Path, to: &Path, temps: &mut Vec<PathBuf>) -> Result<Vec<PathBuf>> {
+        if let Some((copy, options)) = self.smart_options(from)? {
+            return smart_cut_parts(from, to, self.effective, copy, &options, temps);
+        }
+        temps.push(to.to_path_buf());
+        self.run_whole(from, to)?;
+        let ts = to_ts(to, None)?;
+        temps.push(ts.clone());
+        // 演练时不会生成文件
+        if to.exists() {
+            fs::remove_file(to)?;
+        }
+        Ok(vec![ts])
+    }
+
+    /// 智能模式下直接复制的部分和重新编码两端的参数，不能智能截取时返回 `None`
+    fn smart_options(&self, from: &Path) -> Result<Option<(Segment, Vec<(String, String)>)>> {
+        let (CutMode::Smart, Some(copy)) = (self.mode, self.copy) else {
+            return Ok(None);
+        };
+        let video = executor().probe(from)?;
+        let options = edge_options(&video);
+        if options.is_none() {
+            println!("{} 没有和源视频一致的编码器，改为重新编码", self.requested);
+        }
+        Ok(options.map(|x| (copy, x)))
+    }
+
+    /// 不分段，整个片段直接复制或重新编码
+    fn run_whole(&self, from: &Path, to: &Path) -> Result<PathBuf> {
+        let Segment { start, end } = self.effective;
+        match self.mode {
+            CutMode::Quick => cut_quick(from, to, start, end - start),
+            CutMode::Accurate | CutMode::Smart => cut(from, to, start, end - start),
+        }
+    }
+
+    /// 输出对齐或回退的提示
+    pub(crate) fn report(&self, mode: CutMode) {
+        if let Some(copy) = self.copy {
+            println!("{} 直接复制 {}，其余部分重新编码", self.requested, copy);
+        } else if mode != CutMode::Accurate && self.mode == CutMode::Accurate {
+            println!("{} 对齐关键帧偏差过大，改为重新编码", self.requested);
+        } else if self.effective != self.requested {
+            println!("{} 对齐关键帧为 {}，偏差 {:.3}s", self.requested, self.effective, self.drift());
+        }
+    }
+}
+
+/// 视频末尾的容差（秒），按时长等分时浮点误差会让最后的边界略小于时长
+const END_EPSILON: f64 = 0.001;
+
+/// 生成截取计划
+///
+/// 快速模式下开始和结束都对齐到最近的关键帧，视频末尾不做对齐；
+/// 相邻片段共用的边界只对齐一次，偏差超过 `tolerance` 的边界保持原样，
+/// 包含这种边界或对齐后为空的片段改为重新编码，片段之间不会出现重叠或空隙
+///
+/// 智能模式下边界不变，开始之后第一个关键帧到结束之前最后一个关键帧之间直接复制；
+/// 片段内没有完整的 GOP 时改为重新编码
+///
+/// Examples
+///
+/// ```
+/// use bili_video::{plan_cuts, CutMode, Segment};
+///
+/// let keyframes = [0.0, 10.0, 20.0, 30.0];
+/// let segments = [Segment::new(0.0, 12.0), Segment::new(27.0, 35.0)];
+///
+/// let cuts = plan_cuts(&segments, CutMode::Quick, &keyframes, 35.0, None);
+/// assert_eq!(cuts[0].effective, Segment::new(0.0, 10.0));
+/// assert_eq!(cuts[1].effective, Segment::new(30.0, 35.0));
+///
+/// // 第二段偏差 3 秒，超过容差改为重新编码
+/// let cuts = plan_cuts(&segments, CutMode::Quick, &keyframes, 35.0, Some(2.0));
+/// assert_eq!(cuts[0].mode, CutMode::Quick);
+/// assert_eq!(cuts[1].mode, CutMode::Accurate);
+/// assert_eq!(cuts[1].effective, Segment::new(27.0, 35.0));
+///
+/// // 相邻片段的边界一致，只有偏差过大的边界不对齐
+/// let parts = [Segment::new(0.0, 12.0), Segment::new(12.0, 27.0), Segment::new(27.0, 34.9999999)];
+/// let cuts = plan_cuts(&parts, CutMode::Quick, &keyframes, 35.0, Some(2.0));
+/// let effective: Vec<Segment> = cuts.iter().map(|x| x.effective).collect();
+/// assert_eq!(effective, vec![Segment::new(0.0, 10.0), Segment::new(10.0, 27.0), Segment::new(27.0, 35.0)]);
+/// assert_eq!(cuts[0].mode, CutMode::Quick);
+/// assert_eq!(cuts[1].mode, CutMode::Accurate);
+///
+/// // 智能模式只复制关键帧之间的部分
+/// let cuts = plan_cuts(&segments, CutMode::Smart, &keyframes, 35.0, None);
+/// assert_eq!(cuts[0].copy, Some(Segment::new(0.0, 10.0)));
+/// assert_eq!(cuts[1].copy, Some(Segment::new(30.0, 35.0)));
+/// ```
+pub fn plan_cuts(
+    segments: &[Segment],
+    mode: CutMode,
+    keyframes: &[f64],
+    duration: f64,
+    tolerance: Option<f64>,
+) -> Vec<Cut> {
+    match mode {
+        CutMode::Quick => return plan_quick(segments, keyframes, duration, tolerance),
+        CutMode::Accurate | CutMode::Smart => {}
+    }
+    segments
+        .iter()
+        .map(|&requested| {
+            let accurate = Cut { requested, effective: requested, mode: CutMode::Accurate, copy: None };
+            match mode {
+                CutMode::Smart => {
+                    let copy = smart_copy(requested, keyframes, duration);
+                    copy.map_or(accurate, |copy| Cut { mode, copy: Some(copy), ..accurate })
+                }
+                _ => accurate,
+            }
+        })
+        .collect()
+}
+
+/// 片段边界对齐关键帧的结果
+struct Edge {
+    requested: f64,
+    effective: f64,
+    /// 是否落在关键帧或视频末尾，可以直接复制
+    snapped: bool,
+}
+
+fn plan_quick(segments: &[Segment], keyframes: &[f64], duration: f64, tolerance: Option<f64>) -> Vec<Cut> {
+    // 相邻片段共用的边界只对齐一次
+    let mut edges: Vec<Edge> = Vec::new();
+    for t in segments.iter().flat_map(|x| [x.start, x.end]) {
+        if edges.iter().any(|x| x.requested == t) {
+            continue;
+        }
+        let effective = if is_end(t, duration) { t.max(duration) } else { snap_to_keyframe(t, keyframes) };
+        let snapped = tolerance.map_or(true, |tolerance| (effective - t).abs() <= tolerance);
+        edges.push(Edge { requested: t, effective: if snapped { effective } else { t }, snapped });
+    }
+    let find = |edges: &[Edge], t: f64| edges.iter().position(|x| x.requested == t).unwrap();
+
+    // 对齐后为空的片段两端都不对齐，共用边界的相邻片段随之改为重新编码
+    loop {
+        let empty: Vec<usize> = segments
+            .iter()
+            .filter(|x| edges[find(&edges, x.end)].effective <= edges[find(&edges, x.start)].effective)
+            .flat_map(|x| [find(&edges, x.start), find(&edges, x.end)])
+            .filter(|&i| edges[i].snapped)
+            .collect();
+        if empty.is_empty() {
+            break;
+        }
+        for i in empty {
+            edges[i].effective = edges[i].requested;
+            edges[i].snapped = false;
+        }
+    }
+
+    segments
+        .iter()
+        .map(|&requested| {
+            let (start, end) = (&edges[find(&edges, requested.start)], &edges[find(&edges, requested.end)]);
+            let mode = if start.snapped && end.snapped { CutMode::Quick } else { CutMode::Accurate };
+            Cut { requested, effective: Segment::new(start.effective, end.effective), mode, copy: None }
+        })
+        .collect()
+}
+
+/// 是否是视频末尾
+fn is_end(time: f64, duration: f64) -> bool {
+    time >= duration - END_EPSILON
+}
+
+/// 片段内可以直接复制的部分
+fn smart_copy(requested: Segment, keyframes: &[f64], duration: f64) -> Option<Segment> {
+    let start = keyframes.iter().copied().find(|&k| k >= requested.start)?;
+    let end = if is_end(requested.end, duration) {
+        requested.end.max(duration)
+    } else {
+        keyframes.iter().copied().rev().find(|&k| k <= requested.end)?
+    };
+    (start < end).then(|| Segment::new(start, end))
+}
+
+/// 重新编码两端，复制中间部分，再合并成一个文件
+fn smart_cut(from: &Path, to: &Path, segment: Segment, copy: Segment, options: &[(String, String)]) -> Result<PathBuf> {
+    let mut temps: Vec<PathBuf> = Vec::new();
+    let result = smart_cut_parts(from, to, segment, copy, options, &mut temps)
+        .and_then(|ts_slice| concat(&ts_slice, &to.to_path_buf()));
+    for temp in temps {
+        if temp.exists() {
+            let _ = fs::remove_file(temp);
+        }
+    }
+    result
+}
+
+/// 分别截取两端和中间部分并转为 ts，返回按顺序排列的 ts
+fn smart_cut_parts(
+    from: &Path,
+    to: &Path,
+    segment: Segment,
+    copy: Segment,
+    options: &[(String, String)],
+    temps: &mut Vec<PathBuf>,
+) -> Result<Vec<PathBuf>> {
+    let pieces = [
+        (Segment::new(segment.start, copy.start), false),
+        (copy, true),
+        (Segment::new(copy.end, segment.end), false),
+    ];
+
+    let mut ts_slice: Vec<PathBuf> = Vec::new();
+    for (index, (piece, is_copy)) in pieces.into_iter().enumerate() {
+        // 关键帧正好落在边界上时不需要重新编码
+        if piece.duration() <= 0.0 {
+            continue;
+        }
+        let to_piece = to.with_extension(format!("smart{}.mp4", index));
+        temps.push(to_piece.clone());
+        if is_copy {
+            cut_quick(from, &to_piece, piece.start, piece.duration())?;
+        } else {
+            encode_like(from, &to_piece, piece, options)?;
+        }
+        let ts = to_ts(&to_piece, None)?;
+        temps.push(ts.clone());
+        ts_slice.push(ts);
+    }
+    Ok(ts_slice)
+}
+
+/// 使用和源视频相同的编码参数重新编码
+fn encode_like(from: &Path, to: &Path, piece: Segment, options: &[(String, String)]) -> Result<()> {
+    let mut cmd = FfmpegCommand::new();
+    cmd.input(from).seek(piece.start).duration(piece.duration());
+    let output = cmd.output(to);
+    for (key, value) in options {
+        output.option(key, value);
+    }
+    cmd.set_total(Some(piece.duration())).run()
+}
+
+/// 重新编码两端时和源视频一致的输出参数，视频或音频没有对应的编码器时返回 `None`
+///
+/// 编码器、档次、级别、帧率、时间基、像素格式和音频参数都取自源视频，
+/// 保证和直接复制的中间部分可以合并
+fn edge_options(video: &Video) -> Option<Vec<(String, String)>> {
+    let mut options: Vec<(String, String)> = Vec::new();
+    let mut push = |key: &str, value: String| options.push((key.to_string(), value));
+
+    let encoder = video_encoder(video.codec.as_deref()?)?;
+    push("-c:v", encoder.to_string());
+    push("-preset", "veryfast".to_string());
+    push("-crf", "18".to_string());
+    if let Some(profile) = video.profile.as_deref().and_then(|x| profile_option(encoder, x)) {
+        push("-profile:v", profile);
+    }
+    match (encoder, video.level) {
+        ("libx264", Some(level)) => push("-level", format!("{}", level as f64 / 10.0)),
+        // hevc 的级别是 30 倍
+        ("libx265", Some(level)) => push("-x265-params", format!("level-idc={}", level as f64 / 30.0)),
+        _ => {}
+    }
+    if let Some(format) = &video.format {
+        push("-pix_fmt", format.clone());
+    }
+    if let Some(frame_rate) = &video.frame_rate {
+        push("-r", frame_rate.clone());
+    }
+    if let Some(timescale) = video.time_base.as_deref().and_then(|x| x.strip_prefix("1/")) {
+        push("-video_track_timescale", timescale.to_string());
+    }
+
+    if let Some(audio) = video.audios.first() {
+        push("-c:a", audio_encoder(&audio.codec)?.to_string());
+        if audio.sample_rate > 0 {
+            push("-ar", audio.sample_rate.to_string());
+        }
+        if audio.channels > 0 {
+            push("-ac", audio.channels.to_string());
+        }
+        if let Some(bit_rate) = audio.bit_rate {
+            push("-b:a", bit_rate.to_string());
+        }
+    }
+    Some(options)
+}
+
+/// 视频编码对应的编码器
+///
+/// 各部分要先转为 ts 再拼接，只支持 [`to_ts`] 可以处理的 h264 和 hevc
+fn video_encoder(codec: &str) -> Option<&'static str> {
+    match codec {
+        "h264" => Some("libx264"),
+        "hevc" => Some("libx265"),
+        _ => None,
+    }
+}
+
+/// 音频编码对应的编码器
+fn audio_encoder(codec: &str) -> Option<&'static str> {
+    match codec {
+        "aac" => Some("aac"),
+        "mp3" => Some("libmp3lame"),
+        "ac3" => Some("ac3"),
+        "eac3" => Some("eac3"),
+        "opus" => Some("libopus"),
+        "vorbis" => Some("libvorbis"),
+        "flac" => Some("flac"),
+        _ => None,
+    }
+}
+
+/// 编码档次名称转为编码器参数，如 `High` 转为 `high`，`Main 10` 转为 `main10`
+fn profile_option(encoder: &str, profile: &str) -> Option<String> {
+    let profile = match (encoder, profile) {
+        ("libx264", "Baseline" | "Constrained Baseline") => "baseline",
+        ("libx264", "Main") => "main",
+        ("libx264", "High") => "high",
+        ("libx264", "High 10") => "high10",
+        ("libx264", "High 4:2:2") => "high422",
+        ("libx264", "High 4:4:4 Predictive") => "high444",
+        ("libx265", "Main") => "main",
+        ("libx265", "Main 10") => "main10",
+        ("libx265", "Main Still Picture") => "mainstillpicture",
+        _ => return None,
+    };
+    Some(profile.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{AudioStream, Video};
+
+    use super::edge_options;
+
+    #[test]
+    fn test_edge_options() {
+        let video = Video {
+            codec: Some("hevc".into()),
+            profile: Some("Main 10".into()),
+            level: Some(123),
+            format: Some("yuv420p10le".into()),
+            frame_rate: Some("24000/1001".into()),
+            time_base: Some("1/24000".into()),
+            audios: vec![AudioStream { codec: "eac3".into(), channels: 6, sample_rate: 48000, ..Default::default() }],
+            ..Default::default()
+        };
+        let options = edge_options(&video).unwrap();
+        let args: Vec<&str> = options.iter().flat_map(|(k, v)| [k.as_str(), v.as_str()]).collect();
+        assert_eq!(args, vec![
+            "-c:v", "libx265", "-preset", "veryfast", "-crf", "18", "-profile:v", "main10",
+            "-x265-params", "level-idc=4.1", "-pix_fmt", "yuv420p10le", "-r", "24000/1001",
+            "-video_track_timescale", "24000", "-c:a", "eac3", "-ar", "48000", "-ac", "6",
+        ]);
+
+        // 没有对应的编码器时不能智能截取
+        assert!(edge_options(&Video { codec: Some("prores".into()), ..Default::default() }).is_none());
+        assert!(edge_options(&Video { codec: Some("vp9".into()), ..Default::default() }).is_none());
+        let mut video = Video { codec: Some("h264".into()), ..Default::default() };
+        video.audios.push(AudioStream { codec: "truehd".into(), ..Default::default() });
+        assert!(edge_options(&video).is_none());
+    }
+}
@@ -0,0 +1,277 @@
+use std::path::Path;
+
+use anyhow::Result;
+
+use crate::{executor, FfmpegCommand, Segment};
+
+/// 片头片尾检测参数
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Detector {
+    /// 在开头多少秒内查找片头结束
+    pub intro_window: f64,
+    /// 在结尾多少秒内查找片尾开始
+    pub outro_window: f64,
+    /// 静音阈值（dB）
+    pub noise: f64,
+    /// 最短静音时长
+    pub silence_duration: f64,
+    /// 最短黑场时长
+    pub black_duration: f64,
+    /// 黑场像素亮度阈值
+    pub pixel_threshold: f64,
+    /// 离视频开头或结尾太近的边界不作为候选
+    pub margin: f64,
+}
+
+impl Default for Detector {
+    fn default() -> Self {
+        Self {
+            intro_window: 300.0,
+            outro_window: 300.0,
+            noise: -35.0,
+            silence_duration: 0.5,
+            black_duration: 0.3,
+            pixel_threshold: 0.1,
+            margin: 10.0,
+        }
+    }
+}
+
+/// 候选的片头结束或片尾开始时间
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Boundary {
+    pub time: f64,
+    /// 置信度，0 到 1，黑场和静音同时出现时最高
+    pub confidence: f64,
+}
+
+/// 检测结果
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Detection {
+    pub duration: f64,
+    pub intro_end: Option<Boundary>,
+    pub outro_start: Option<Boundary>,
+    /// 检测到的静音片段
+    pub silences: Vec<Segment>,
+    /// 检测到的黑场片段
+    pub blacks: Vec<Segment>,
+}
+
+impl Detection {
+    /// 置信度不低于 `min_confidence` 的片头片尾，可以直接作为 `exclude_segments`
+    ///
+    /// Examples
+    ///
+    /// ```
+    /// use bili_video::{Boundary, Detection, Segment};
+    ///
+    /// let detection = Detection {
+    ///     duration: 2700.0,
+    ///     intro_end: Some(Boundary { time: 89.4, confidence: 0.9 }),
+    ///     outro_start: Some(Boundary { time: 2610.0, confidence: 0.4 }),
+    ///     ..Default::default()
+    /// };
+    /// assert_eq!(detection.exclude_segments(0.5), vec![Segment::new(0.0, 89.4)]);
+    /// assert_eq!(detection.exclude_segments(0.3).len(), 2);
+    /// ```
+    pub fn exclude_segments(&self, min_confidence: f64) -> Vec<Segment> {
+        let mut segments = Vec::new();
+        if let Some(b) = self.intro_end.filter(|x| x.confidence >= min_confidence) {
+            segments.push(Segment::new(0.0, b.time));
+        }
+        if let Some(b) = self.outro_start.filter(|x| x.confidence >= min_confidence) {
+            segments.push(Segment::new(b.time, self.duration));
+        }
+        segments
+    }
+}
+
+impl Detector {
+    fn black_filter(&self) -> String {
+        format!("blackdetect=d={}:pix_th={}", self.black_duration, self.pixel_threshold)
+    }
+
+    fn silence_filter(&self) -> String {
+        format!("silencedetect=noise={}dB:d={}", self.noise, self.silence_duration)
+    }
+
+    /// 分析视频的开头和结尾，返回片头结束和片尾开始的候选
+    ///
+    /// Examples
+    ///
+    /// ```ignore
+    /// use bili_video::Detector;
+    ///
+    /// let detection = Detector::default().detect("/tmp/S01E01.mp4").unwrap();
+    /// println!("{:?}", detection.intro_end);
+    /// ```
+    pub fn detect<P: AsRef<Path>>(&self, path: P) -> Result<Detection> {
+        let duration = executor().probe(path.as_ref())?.duration;
+        let intro = Segment::new(0.0, self.intro_window.min(duration));
+        let outro = Segment::new((duration - self.outro_window).max(intro.end), duration);
+
+        let mut silences = Vec::new();
+        let mut blacks = Vec::new();
+        for window in [intro, outro] {
+            if window.duration() <= 0.0 {
+                continue;
+            }
+            let stderr = self.analyze(path.as_ref(), window)?;
+            // 输入端 seek 后日志中的时间从 0 开始
+            let shift = |x: Segment| Segment::new(x.start + window.start, x.end + window.start);
+            silences.extend(parse_silence(&stderr, window.duration()).into_iter().map(shift));
+            blacks.extend(parse_black(&stderr).into_iter().map(shift));
+        }
+
+        let candidates = candidates(&silences, &blacks);
+        let intro_end = best(candidates.iter().filter_map(|(x, c)| {
+            (x.end >= self.margin && x.end <= intro.end).then_some(Boundary { time: x.end, confidence: *c })
+        }));
+        let outro_start = best(candidates.iter().filter_map(|(x, c)| {
+            (x.start >= outro.start && x.start <= duration - self.margin)
+                .then_some(Boundary { time: x.start, confidence: *c })
+        }));
+        Ok(Detection { duration, intro_end, outro_start, silences, blacks })
+    }
+
+    fn analyze(&self, path: &Path, window: Segment) -> Result<String> {
+        let null = if cfg!(windows) { "NUL" } else { "/dev/null" };
+        let mut cmd = FfmpegCommand::new();
+        cmd.input(path).seek(window.start).duration(window.duration());
+        cmd.output(null)
+            .video_filter(&self.black_filter())
+            .audio_filter(&self.silence_filter())
+            .format("null");
+        cmd.set_total(Some(window.duration())).analyze()
+    }
+}
+
+/// 解析 `silencedetect` 的输出，没有结束的静音持续到 `duration`
+///
+/// Examples
+///
+/// ```
+/// use bili_video::{parse_silence, Segment};
+///
+/// let stderr = "\
+/// [silencedetect @ 0x1] silence_start: 87.52
+/// [silencedetect @ 0x1] silence_end: 89.4 | silence_duration: 1.88
+/// [silencedetect @ 0x1] silence_start: 295.1
+/// ";
+/// assert_eq!(parse_silence(stderr, 300.0), vec![
+///     Segment::new(87.52, 89.4),
+///     Segment::new(295.1, 300.0),
+/// ]);
+/// ```
+pub fn parse_silence(stderr: &str, duration: f64) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    let mut start = None;
+    for line in stderr.lines() {
+        if let Some(x) = value_after(line, "silence_start:") {
+            start = Some(x.max(0.0));
+        } else if let Some(end) = value_after(line, "silence_end:") {
+            segments.push(Segment::new(start.take().unwrap_or(0.0), end));
+        }
+    }
+    if let Some(start) = start {
+        segments.push(Segment::new(start, duration));
+    }
+    segments
+}
+
+/// 解析 `blackdetect` 的输出
+///
+/// Examples
+///
+/// ```
+/// use bili_video::{parse_black, Segment};
+///
+/// let stderr = "[blackdetect @ 0x1] black_start:88.12 black_end:89.4 black_duration:1.28";
+/// assert_eq!(parse_black(stderr), vec![Segment::new(88.12, 89.4)]);
+/// ```
+pub fn parse_black(stderr: &str) -> Vec<Segment> {
+    stderr
+        .lines()
+        .filter_map(|line| {
+            let start = value_after(line, "black_start:")?;
+            let end = value_after(line, "black_end:")?;
+            Some(Segment::new(start, end))
+        })
+        .collect()
+}
+
+/// 读取 `key` 后面的数字
+pub(crate) fn value_after(line: &str, key: &str) -> Option<f64> {
+    let rest = &line[line.find(key)? + key.len()..];
+    rest.split_whitespace().next()?.parse().ok()
+}
+
+/// 黑场和静音重叠时置信度最高，只有黑场其次，只有静音最低；
+/// 间隔越长越可能是片头片尾和正片的分界
+fn candidates(silences: &[Segment], blacks: &[Segment]) -> Vec<(Segment, f64)> {
+    let overlaps = |a: &Segment, b: &Segment| a.start <= b.end && b.start <= a.end;
+    let bonus = |x: &Segment| (x.duration() / 10.0).min(0.1);
+
+    let mut candidates = Vec::new();
+    for black in blacks {
+        match silences.iter().find(|s| overlaps(black, s)) {
+            Some(silence) => {
+                let merged = Segment::new(black.start.min(silence.start), black.end.max(silence.end));
+                candidates.push((merged, 0.8 + bonus(&merged)));
+            }
+            None => candidates.push((*black, 0.5 + bonus(black))),
+        }
+    }
+    for silence in silences {
+        if !blacks.iter().any(|b| overlaps(b, silence)) {
+            candidates.push((*silence, 0.3 + bonus(silence)));
+        }
+    }
+    candidates
+}
+
+/// 置信度最高的候选
+fn best<I: Iterator<Item = Boundary>>(boundaries: I) -> Option<Boundary> {
+    boundaries.max_by(|a, b| a.confidence.total_cmp(&b.confidence))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::{with_executor, RecordingExecutor, Segment, Video};
+
+    use super::Detector;
+
+    #[test]
+    fn test_detect() {
+        let rec = Arc::new(RecordingExecutor::new());
+        rec.add_video("/tmp/ep.mp4", Video { duration: 600.0, ..Default::default() });
+        // 两次分析返回相同的日志：开头窗口 [0, 300]，结尾窗口 [300, 600]
+        rec.set_stderr("\
+[blackdetect @ 0x1] black_start:2 black_end:3 black_duration:1
+[silencedetect @ 0x1] silence_start: 88.5
+[silencedetect @ 0x1] silence_end: 90 | silence_duration: 1.5
+[blackdetect @ 0x1] black_start:89 black_end:90.2 black_duration:1.2
+[silencedetect @ 0x1] silence_start: 150
+[silencedetect @ 0x1] silence_end: 151 | silence_duration: 1
+");
+        let detection = with_executor(rec.clone(), || Detector::default().detect("/tmp/ep.mp4").unwrap());
+
+        let calls = rec.calls();
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[1][1..5], ["-ss", "00:05:00.000", "-t", "00:05:00.000"]);
+
+        // 开头的黑场离视频开头太近，不作为候选
+        let intro = detection.intro_end.unwrap();
+        assert_eq!(intro.time, 90.2);
+        assert!(intro.confidence > 0.8);
+        // 结尾窗口的时间加上窗口开始时间
+        let outro = detection.outro_start.unwrap();
+        assert_eq!(outro.time, 388.5);
+        assert_eq!(detection.exclude_segments(0.5), vec![
+            Segment::new(0.0, 90.2),
+            Segment::new(388.5, 600.0),
+        ]);
+    }
+}
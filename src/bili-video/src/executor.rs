@@ -0,0 +1,304 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use anyhow::{anyhow, Result};
+
+use crate::{
+    probe_keyframes,
+    process::{analyze_ffmpeg, spawn_ffmpeg},
+    FfmpegCommand, Video,
+};
+
+static EXECUTOR: Mutex<Option<Arc<dyn Executor>>> = Mutex::new(None);
+
+thread_local! {
+    static SCOPED: RefCell<Option<Arc<dyn Executor>>> = const { RefCell::new(None) };
+}
+
+/// 命令执行器
+///
+/// 所有 ffmpeg/ffprobe 调用和视频信息读取都会经过执行器，
+/// 替换执行器即可在没有 ffmpeg 的环境中测试或只打印执行计划
+pub trait Executor: Send + Sync {
+    /// 执行 ffmpeg 命令
+    fn run(&self, cmd: &FfmpegCommand) -> Result<()>;
+
+    /// 执行命令并返回标准输出，如 ffprobe
+    fn output(&self, argv: &[String]) -> Result<String>;
+
+    /// 执行 ffmpeg 分析命令并返回标准错误输出
+    fn analyze(&self, cmd: &FfmpegCommand) -> Result<String>;
+
+    /// 读取视频信息
+    fn probe(&self, path: &Path) -> Result<Video> {
+        Video::from(path)
+    }
+
+    /// 读取视频关键帧的时间
+    fn keyframes(&self, path: &Path) -> Result<Vec<f64>> {
+        Ok(probe_keyframes(path)?)
+    }
+
+    /// 文件是否存在
+    fn exists(&self, path: &Path) -> bool {
+        path.exists()
+    }
+}
+
+/// 获取当前执行器
+///
+/// 优先使用 `with_executor` 设置的执行器，其次是 `set_executor`，默认为 `SystemExecutor`
+pub fn executor() -> Arc<dyn Executor> {
+    if let Some(e) = SCOPED.with(|x| x.borrow().clone()) {
+        return e;
+    }
+    EXECUTOR.lock().unwrap().clone().unwrap_or_else(|| Arc::new(SystemExecutor))
+}
+
+/// 设置进程内的执行器
+pub fn set_executor(executor: Arc<dyn Executor>) {
+    *EXECUTOR.lock().unwrap() = Some(executor);
+}
+
+/// 在当前线程中使用指定执行器执行 `f`
+///
+/// Examples
+///
+/// ```
+/// use std::sync::Arc;
+/// use bili_video::{with_executor, FfmpegCommand, RecordingExecutor};
+///
+/// let rec = Arc::new(RecordingExecutor::new());
+/// with_executor(rec.clone(), || {
+///     let mut cmd = FfmpegCommand::new();
+///     cmd.input("/tmp/in.mp4");
+///     cmd.output("/tmp/bili-video-doc-out.mp4").codec("copy");
+///     cmd.run().unwrap();
+/// });
+/// assert_eq!(rec.calls(), vec![vec![
+///     "ffmpeg", "-i", "/tmp/in.mp4", "-c", "copy", "/tmp/bili-video-doc-out.mp4",
+/// ]]);
+/// # std::fs::remove_file("/tmp/bili-video-doc-out.mp4").unwrap();
+/// ```
+pub fn with_executor<F, R>(executor: Arc<dyn Executor>, f: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    let prev = SCOPED.with(|x| x.replace(Some(executor)));
+    // 即使 f panic 也要恢复之前的执行器
+    struct Restore(Option<Arc<dyn Executor>>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            let prev = self.0.take();
+            SCOPED.with(|x| *x.borrow_mut() = prev);
+        }
+    }
+    let _restore = Restore(prev);
+    f()
+}
+
+/// 默认执行器，直接调用系统中的 ffmpeg/ffprobe
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemExecutor;
+
+impl Executor for SystemExecutor {
+    fn run(&self, cmd: &FfmpegCommand) -> Result<()> {
+        spawn_ffmpeg(cmd)
+    }
+
+    fn output(&self, argv: &[String]) -> Result<String> {
+        lazycmd::output(argv)
+    }
+
+    fn analyze(&self, cmd: &FfmpegCommand) -> Result<String> {
+        analyze_ffmpeg(cmd)
+    }
+}
+
+/// 录制执行器，只记录命令不执行，用于测试
+///
+/// 执行时会创建空的输出文件，方便后续步骤继续处理；
+/// 输出文件的编码沿用第一个输入文件，未知时假定为 h264
+#[derive(Debug, Default)]
+pub struct RecordingExecutor {
+    calls: Mutex<Vec<Vec<String>>>,
+    videos: Mutex<HashMap<PathBuf, Video>>,
+    keyframes: Mutex<HashMap<PathBuf, Vec<f64>>>,
+    stdout: Mutex<String>,
+    stderr: Mutex<String>,
+}
+
+impl RecordingExecutor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 设置 `probe` 返回的视频信息
+    pub fn add_video<P: AsRef<Path>>(&self, path: P, video: Video) -> &Self {
+        self.videos.lock().unwrap().insert(path.as_ref().to_path_buf(), video);
+        self
+    }
+
+    /// 设置 `keyframes` 返回的关键帧，未设置时返回空列表
+    pub fn add_keyframes<P: AsRef<Path>>(&self, path: P, keyframes: Vec<f64>) -> &Self {
+        self.keyframes.lock().unwrap().insert(path.as_ref().to_path_buf(), keyframes);
+        self
+    }
+
+    /// 设置 `output` 返回的标准输出
+    pub fn set_output(&self, stdout: &str) -> &Self {
+        *self.stdout.lock().unwrap() = stdout.to_string();
+        self
+    }
+
+    /// 设置 `analyze` 返回的标准错误输出
+    pub fn set_stderr(&self, stderr: &str) -> &Self {
+        *self.stderr.lock().unwrap() = stderr.to_string();
+        self
+    }
+
+    /// 按顺序返回所有执行过的命令
+    pub fn calls(&self) -> Vec<Vec<String>> {
+        self.calls.lock().unwrap().clone()
+    }
+}
+
+impl Executor for RecordingExecutor {
+    fn run(&self, cmd: &FfmpegCommand) -> Result<()> {
+        self.calls.lock().unwrap().push(cmd.argv());
+        let mut videos = self.videos.lock().unwrap();
+        let codec = cmd
+            .inputs()
+            .first()
+            .and_then(|x| videos.get(x))
+            .and_then(|x| x.codec.clone())
+            .unwrap_or("h264".to_string());
+        for output in cmd.outputs() {
+            fs::write(&output, "")?;
+            videos.entry(output.clone()).or_insert(Video {
+                duration: cmd.total().unwrap_or_default(),
+                codec: Some(codec.clone()),
+                path: output.to_string_lossy().into_owned(),
+                ..Default::default()
+            });
+        }
+        Ok(())
+    }
+
+    fn output(&self, argv: &[String]) -> Result<String> {
+        self.calls.lock().unwrap().push(argv.to_vec());
+        Ok(self.stdout.lock().unwrap().clone())
+    }
+
+    fn analyze(&self, cmd: &FfmpegCommand) -> Result<String> {
+        self.calls.lock().unwrap().push(cmd.argv());
+        Ok(self.stderr.lock().unwrap().clone())
+    }
+
+    fn probe(&self, path: &Path) -> Result<Video> {
+        self.videos
+            .lock()
+            .unwrap()
+            .get(path)
+            .cloned()
+            .ok_or(anyhow!("{:?} not probed", path))
+    }
+
+    fn keyframes(&self, path: &Path) -> Result<Vec<f64>> {
+        Ok(self.keyframes.lock().unwrap().get(path).cloned().unwrap_or_default())
+    }
+}
+
+/// 演练执行器，只打印执行计划
+///
+/// 读取类的命令仍然会执行，计划生成的文件会被视为已存在，编码假定为 h264
+#[derive(Debug, Default)]
+pub struct DryRunExecutor {
+    planned: Mutex<HashMap<PathBuf, f64>>,
+}
+
+impl DryRunExecutor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Executor for DryRunExecutor {
+    fn run(&self, cmd: &FfmpegCommand) -> Result<()> {
+        println!("{}", shell_join(&cmd.argv()));
+        let mut planned = self.planned.lock().unwrap();
+        for output in cmd.outputs() {
+            planned.insert(output, cmd.total().unwrap_or_default());
+        }
+        Ok(())
+    }
+
+    fn output(&self, argv: &[String]) -> Result<String> {
+        lazycmd::output(argv)
+    }
+
+    fn analyze(&self, cmd: &FfmpegCommand) -> Result<String> {
+        // 计划中的文件还未生成，无法分析
+        let planned = self.planned.lock().unwrap();
+        if cmd.inputs().iter().any(|x| planned.contains_key(x)) {
+            println!("{}", shell_join(&cmd.argv()));
+            return Ok(String::new());
+        }
+        drop(planned);
+        analyze_ffmpeg(cmd)
+    }
+
+    fn probe(&self, path: &Path) -> Result<Video> {
+        // 计划中的文件还未生成，无法探测
+        if let Some(duration) = self.planned.lock().unwrap().get(path) {
+            return Ok(Video {
+                duration: *duration,
+                codec: Some("h264".to_string()),
+                path: path.to_string_lossy().into_owned(),
+                ..Default::default()
+            });
+        }
+        Video::from(path)
+    }
+
+    fn keyframes(&self, path: &Path) -> Result<Vec<f64>> {
+        // 计划中的文件没有关键帧信息，按请求的时间截取
+        if self.planned.lock().unwrap().contains_key(path) {
+            return Ok(Vec::new());
+        }
+        Ok(probe_keyframes(path)?)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        path.exists() || self.planned.lock().unwrap().contains_key(path)
+    }
+}
+
+/// 拼接成可以直接复制到终端执行的命令
+///
+/// Examples
+///
+/// ```
+/// let cmd = bili_video::shell_join(&["ffmpeg", "-i", "/tmp/a b.mp4", "/tmp/it's.mp4"]);
+/// assert_eq!(cmd, r#"ffmpeg -i '/tmp/a b.mp4' '/tmp/it'\''s.mp4'"#);
+/// ```
+pub fn shell_join<S: AsRef<str>>(argv: &[S]) -> String {
+    argv.iter()
+        .map(|x| {
+            let x = x.as_ref();
+            let safe = !x.is_empty()
+                && x.chars().all(|c| c.is_alphanumeric() || "-_./:=,+%@".contains(c));
+            if safe {
+                x.to_string()
+            } else {
+                format!("'{}'", x.replace('\'', "'\\''"))
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
@@ -7,6 +7,8 @@ use std::{
 use anyhow::{anyhow, Result};
 use lazytool::path::{must_get_filename, must_to_string};
 
+use crate::{executor, transcode, FfmpegCommand, TranscodeProfile};
+
 /// 视频转为 ts
 ///
 /// Examples
@@ -19,7 +21,8 @@ use lazytool::path::{must_get_filename, must_to_string};
 pub fn to_ts<P: AsRef<Path>>(from: P, to: Option<P>) -> Result<PathBuf> {
     let from_path = get_path_string(&from)?;
     let to_path = get_to(&from, to.as_ref(), "ts")?;
-    let codec = get_codec(from)?;
+    let video = executor().probe(from.as_ref())?;
+    let codec = video.codec.unwrap_or_default();
     let bsf_filter = match codec.as_str() {
         "h264" => "h264_mp4toannexb",
         "hevc" => "hevc_mp4toannexb",
@@ -27,11 +30,10 @@ pub fn to_ts<P: AsRef<Path>>(from: P, to: Option<P>) -> Result<PathBuf> {
             return Err(anyhow!("Unsupported codec: {}", codec));
         }
     };
-    let cmds = [
-        "ffmpeg", "-i", &from_path, "-codec", "copy", "-bsf:v", bsf_filter, "-f", "mpegts",
-        &to_path,
-    ];
-    lazycmd::spawn(cmds)?;
+    let mut cmd = FfmpegCommand::new();
+    cmd.input(&from_path);
+    cmd.output(&to_path).codec("copy").bsf("v", bsf_filter).format("mpegts");
+    cmd.set_total(Some(video.duration)).run()?;
     Ok(PathBuf::from(to_path))
 }
 
@@ -69,17 +71,15 @@ where
     // -hls_list_size 0 表示在 M3U8 文件中保留所有 TS 文件的索引；
     // -hls_allow_cache 1 允许客户端缓存 TS 文件；
     // -hls_segment_filename 这个参数指定 TS 文件的命名模板
-    let cmds = [
-        "ffmpeg",
-        "-i", &from_path,
-        "-hls_time", hls_s.as_str(),
-        "-hls_list_size", "0",
-        "-hls_allow_cache", "1",
-        "-hls_segment_filename", &ts_filename_template,
-        &to_path,
-    ];
+    let mut cmd = FfmpegCommand::new();
+    cmd.input(&from_path);
+    cmd.output(&to_path)
+        .option("-hls_time", &hls_s)
+        .option("-hls_list_size", "0")
+        .option("-hls_allow_cache", "1")
+        .option("-hls_segment_filename", &ts_filename_template);
 
-    lazycmd::spawn(cmds)?;
+    cmd.set_total(probe_duration(&from_path)).run()?;
     Ok(PathBuf::from(to_path))
 }
 
@@ -105,24 +105,17 @@ where
     } else {
         to_path = must_to_string(from.as_ref().with_extension("mp4"));
     }
-    let mut cmds = [
-        "ffmpeg",
-    ].to_vec();
+    let mut cmd = FfmpegCommand::new();
+    let input = cmd.input(&from_path);
     // 如果是 m3u8 增加 -allowed_extensions ALL 表示允许所有扩展名，防止因扩展名问题导致读取 m3u8 文件失败接口
     // 需要放在 -i 参数之前
     if from_path.ends_with(".m3u8") {
-        cmds.push("-allowed_extensions");
-        cmds.push("ALL");
+        input.option("-allowed_extensions", "ALL");
     }
-    cmds.extend([
-        "-i", &from_path,
-        "-c:v", "copy",
-        "-c:a", "copy",
-        &to_path
-    ]);
-    println!("{:?}", &cmds);
+    cmd.output(&to_path).video_codec("copy").audio_codec("copy");
+    println!("{:?}", cmd.argv());
 
-    lazycmd::spawn(cmds)?;
+    cmd.set_total(probe_duration(&from_path)).run()?;
     Ok(PathBuf::from(to_path))
 }
 
@@ -153,16 +146,16 @@ where
         }
     }
     if is_video(&from_path) {
-        let cmds = [
-            "ffmpeg", "-i", &from_path, "-acodec", "libmp3lame", "-q:a", "0", "-map", "a", &to_path,
-        ];
-        lazycmd::spawn(cmds)?;
+        let mut cmd = FfmpegCommand::new();
+        cmd.input(&from_path);
+        cmd.output(&to_path).audio_codec("libmp3lame").option("-q:a", "0").map("a");
+        cmd.set_total(probe_duration(&from_path)).run()?;
         Ok(PathBuf::from(to_path))
     } else if is_audio(&from_path) {
-        let cmds = [
-            "ffmpeg", "-i", &from_path, "-acodec", "libmp3lame", "-q:a", "0", &to_path,
-        ];
-        lazycmd::spawn(cmds)?;
+        let mut cmd = FfmpegCommand::new();
+        cmd.input(&from_path);
+        cmd.output(&to_path).audio_codec("libmp3lame").option("-q:a", "0");
+        cmd.set_total(probe_duration(&from_path)).run()?;
         Ok(PathBuf::from(to_path))
     } else {
         Err(anyhow!("can not trans {from_path}"))
@@ -210,24 +203,18 @@ where
     T: AsRef<Path>,
 {
     let from_path = get_path_string(&from)?;
-    let to_path = to.as_ref().to_str().unwrap().to_string();
-    let cmds = [
-        "ffmpeg",
-        "-ss",
-        &float_to_time_format(start),
-        "-t",
-        &float_to_time_format(time),
-        "-i",
-        &from_path,
-        "-copyts",
-        &to_path,
-    ];
-    lazycmd::spawn(cmds)?;
+    let mut cmd = FfmpegCommand::new();
+    cmd.input(&from_path).seek(start).duration(time);
+    cmd.output(&to).flag("-copyts");
+    // -copyts 保留原始时间戳，进度中的输出时间从 start 开始
+    cmd.set_total(Some(time)).set_progress_offset(start).run()?;
     Ok(to.as_ref().to_path_buf())
 }
 
 /// 截取视频(速度较快)
 ///
+/// 和 `cut` 一样在输入端 seek，直接复制流不重新编码，起点会落在关键帧上
+///
 /// Examples
 ///
 /// ```ignore
@@ -242,20 +229,10 @@ where
     T: AsRef<Path>,
 {
     let from_path = get_path_string(&from)?;
-    let to_path = to.as_ref().to_str().unwrap().to_string();
-    let cmds = [
-        "ffmpeg",
-        "-i",
-        &from_path,
-        "-ss",
-        &float_to_time_format(start),
-        "-t",
-        &float_to_time_format(time),
-        "-c",
-        "copy",
-        &to_path,
-    ];
-    lazycmd::spawn(cmds)?;
+    let mut cmd = FfmpegCommand::new();
+    cmd.input(&from_path).seek(start).duration(time);
+    cmd.output(&to).codec("copy");
+    cmd.set_total(Some(time)).run()?;
     Ok(to.as_ref().to_path_buf())
 }
 
@@ -280,77 +257,36 @@ where
     I: IntoIterator<Item = P>,
     P: AsRef<Path>,
 {
-    let to_path = to.as_ref().to_str().unwrap().to_string();
     let cache_dir = to.as_ref().parent().unwrap();
     let concat_path = cache_dir.join(format!("concat-{}", get_timestamp()));
     let mut concat = String::new();
+    let mut total = 0.0;
     for f in from {
         let fp = get_path_string(&f)?;
-        concat.push_str(format!("file '{}'\n", &fp).as_str());
+        total += probe_duration(&fp).unwrap_or_default();
+        // 单引号需要转义，否则路径中包含单引号时无法读取
+        concat.push_str(format!("file '{}'\n", fp.replace('\'', "'\\''")).as_str());
     }
     fs::write(&concat_path, concat)?;
     // cmd = f"ffmpeg -f concat -safe 0 -i {tmpfile} -c copy -bsf:a aac_adtstoasc {output}"
-    let cmds = [
-        "ffmpeg",
-        "-f",
-        "concat",
-        "-safe",
-        "0",
-        "-i",
-        &get_path_string(&concat_path)?,
-        "-c",
-        "copy",
-        "-bsf:a",
-        "aac_adtstoasc",
-        // "-movflags", "+faststart",
-        &to_path,
-    ];
-    lazycmd::spawn(cmds)?;
+    let mut cmd = FfmpegCommand::new();
+    cmd.input(&concat_path).format("concat").option("-safe", "0");
+    cmd.output(&to)
+        .codec("copy")
+        // .option("-movflags", "+faststart")
+        .bsf("a", "aac_adtstoasc");
+    cmd.set_total(Some(total)).run()?;
     fs::remove_file(concat_path)?;
     Ok(to.as_ref().to_path_buf())
 }
 
-/// 转码成 1080p 固定格式视频
+/// 转码成 1080p 固定格式视频，等同于使用默认的 `1080p` 转码配置
 pub fn transcode_1080<F, T>(from: F, to: T) -> Result<()>
 where
     F: AsRef<Path>,
     T: AsRef<Path>,
 {
-    let from_path = lazytool::path::must_to_string(from);
-    let to_path = lazytool::path::must_to_string(to);
-
-    let cmds = [
-        "ffmpeg",
-        "-i",
-        &from_path,
-        "-c:v",
-        "libx264",
-        "-preset",
-        "veryfast",
-        "-maxrate",
-        "17185k",
-        "-bufsize",
-        "34370k",
-        "-crf",
-        "23",
-        "-r",
-        "25",
-        // "-s",
-        // "1920x1080",
-        "-vf",
-        "scale=1920:1080:force_original_aspect_ratio=decrease,pad=1920:1080:(ow-iw)/2:(oh-ih)/2",
-        "-c:a",
-        "aac",
-        "-b:a",
-        "319k",
-        "-ar",
-        "48000",
-        "-ac",
-        "2",
-        &to_path,
-    ];
-    lazycmd::spawn(cmds)?;
-    Ok(())
+    transcode(from, to, &TranscodeProfile::default())
 }
 
 /// 将时间浮点数转为时间格式字符串
@@ -375,48 +311,102 @@ pub fn float_to_time_format(seconds: f64) -> String {
     format!("{:02}:{:02}:{:02}", hours, minutes, secs)
 }
 
-/// 截图
+/// 将时间浮点数转为精确到毫秒的时间格式字符串
 ///
 /// Examples
 ///
 /// ```
+/// let out = bili_video::format_timestamp(89.4);
+/// assert_eq!(out, "00:01:29.400");
+///
+/// let out = bili_video::format_timestamp(3671.25);
+/// assert_eq!(out, "01:01:11.250");
+/// ```
+pub fn format_timestamp(seconds: f64) -> String {
+    let total_millis = (seconds.abs() * 1000.0).round() as u64;
+    let hours = total_millis / 3_600_000;
+    let minutes = (total_millis % 3_600_000) / 60_000;
+    let secs = (total_millis % 60_000) / 1000;
+    let millis = total_millis % 1000;
+    format!("{:02}:{:02}:{:02}.{:03}", hours, minutes, secs, millis)
+}
+
+/// 解析时间字符串为秒数
+///
+/// 支持 `ss.mmm`、`mm:ss.mmm` 和 `hh:mm:ss.mmm`
+///
+/// Examples
+///
+/// ```
+/// use bili_video::parse_timestamp;
+///
+/// assert_eq!(parse_timestamp("89.4").unwrap(), 89.4);
+/// assert_eq!(parse_timestamp("01:29.4").unwrap(), 89.4);
+/// assert_eq!(parse_timestamp("01:01:11.5").unwrap(), 3671.5);
+/// assert!(parse_timestamp("00:61:00").is_err());
+/// assert!(parse_timestamp("-1").is_err());
+/// ```
+pub fn parse_timestamp(s: &str) -> Result<f64> {
+    let parts: Vec<&str> = s.trim().split(':').collect();
+    if parts.len() > 3 {
+        return Err(anyhow!("invalid timestamp: {}", s));
+    }
+    let mut total = 0.0;
+    for (index, part) in parts.iter().enumerate() {
+        let value: f64 = if index == parts.len() - 1 {
+            part.parse().map_err(|_| anyhow!("invalid timestamp: {}", s))?
+        } else {
+            part.parse::<u64>().map_err(|_| anyhow!("invalid timestamp: {}", s))? as f64
+        };
+        // 分和秒不能超过 60
+        if !value.is_finite() || value < 0.0 || (index > 0 && value >= 60.0) {
+            return Err(anyhow!("invalid timestamp: {}", s));
+        }
+        total = total * 60.0 + value;
+    }
+    // 精确到毫秒
+    Ok((total * 1000.0).round() / 1000.0)
+}
+
+/// 截图
+///
+/// Examples
+///
+/// ```ignore
+/// use bili_video::screenshot;
+///
+/// // 截取第 10 秒的画面
+/// screenshot("/tmp/test.mp4", "/tmp/test.png", 10.0).unwrap()
 /// ```
 pub fn screenshot<F, T>(from: F, to: T, start: f64) -> Result<PathBuf>
 where
     F: AsRef<Path>,
     T: AsRef<Path>,
 {
-    let cmd = format!(
-        "ffmpeg -i {} -ss {} -vframes 1 -q:v 1 {}",
-        must_to_string(from),
-        float_to_time_format(start),
-        must_to_string(&to),
-    );
-    lazycmd::spawn(cmd.split(" "))?;
+    let mut cmd = FfmpegCommand::new();
+    cmd.input(&from).seek(start);
+    cmd.output(&to).frames(1).option("-q:v", "1");
+    cmd.run()?;
     Ok(to.as_ref().to_path_buf())
 }
 
 /// 获取视频 codec_name
 pub fn get_codec<P: AsRef<Path>>(path: P) -> Result<String> {
-    let path = get_path_string(path)?;
-    let args = [
-        "ffprobe",
-        "-v",
-        "error",
-        "-select_streams",
-        "v:0",
-        "-show_entries",
-        "stream=codec_name",
-        "-of",
-        "default=noprint_wrappers=1:nokey=1",
-        &path,
-    ];
-    lazycmd::output(args)
+    let path = path.as_ref();
+    executor()
+        .probe(path)?
+        .codec
+        .ok_or(anyhow!("{:?} has no video codec", path))
+}
+
+/// 获取视频时长，用于计算进度
+fn probe_duration<P: AsRef<Path>>(path: P) -> Option<f64> {
+    executor().probe(path.as_ref()).ok().map(|v| v.duration)
 }
 
 fn get_path_string<P: AsRef<Path>>(from: P) -> Result<String> {
     let from = from.as_ref();
-    if !from.exists() {
+    if !executor().exists(from) {
         return Err(anyhow!("{:?} not foud", from));
     }
     Ok(from.to_str().unwrap().to_string())
@@ -424,7 +414,7 @@ fn get_path_string<P: AsRef<Path>>(from: P) -> Result<String> {
 
 fn get_to<P: AsRef<Path>>(from: P, to: Option<P>, ext: &str) -> Result<String> {
     let from = from.as_ref();
-    if !from.exists() {
+    if !executor().exists(from) {
         return Err(anyhow!("{:?} not foud", from));
     }
     let to_path: String;
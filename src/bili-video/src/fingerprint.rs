@@ -0,0 +1,462 @@
+extern crate ffmpeg_next as ffmpeg;
+
+use std::{
+    f32::consts::PI,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Result};
+use ffmpeg::{
+    ffi::AV_NOPTS_VALUE,
+    format::{sample, Sample},
+    frame,
+    media::Type,
+    software::resampling,
+    ChannelLayout,
+};
+
+use crate::{executor, Segment};
+
+/// 指纹使用的采样率
+const SAMPLE_RATE: u32 = 11025;
+
+/// 每帧的采样数
+const FRAME_SIZE: usize = 4096;
+
+/// 相邻帧的间隔，帧之间重叠 2/3
+const HOP: usize = FRAME_SIZE / 3;
+
+/// 参与计算的频率范围，分成 33 个频带得到 32 位哈希
+const MIN_FREQ: f32 = 300.0;
+const MAX_FREQ: f32 = 2000.0;
+const BANDS: usize = 33;
+
+/// 两帧哈希不同的位数不超过该值时认为相同
+const MAX_BIT_ERRORS: u32 = 10;
+
+/// 匹配片段中允许连续不相同的帧数
+const MAX_GAP: usize = 8;
+
+/// 音频指纹，每帧一个 32 位哈希
+///
+/// 和 chromaprint 一样把音频降采样到 11025 Hz 单声道，
+/// 用相邻频带能量差在相邻帧之间的变化作为哈希的每一位
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Fingerprint {
+    /// 第一个采样在视频中的时间
+    pub start: f64,
+    pub hashes: Vec<u32>,
+}
+
+impl Fingerprint {
+    /// 每个哈希之间的时间间隔
+    pub fn step() -> f64 {
+        HOP as f64 / SAMPLE_RATE as f64
+    }
+
+    /// 第 `index` 个哈希对应的时间
+    pub fn time(&self, index: usize) -> f64 {
+        self.start + (index + 1) as f64 * Self::step()
+    }
+
+    /// 从 11025 Hz 单声道采样计算指纹
+    pub fn from_pcm(samples: &[f32], start: f64) -> Self {
+        let window: Vec<f32> = (0..FRAME_SIZE)
+            .map(|i| 0.5 - 0.5 * (2.0 * PI * i as f32 / FRAME_SIZE as f32).cos())
+            .collect();
+        let edges: Vec<usize> = (0..=BANDS)
+            .map(|b| {
+                let freq = MIN_FREQ * (MAX_FREQ / MIN_FREQ).powf(b as f32 / BANDS as f32);
+                (freq * FRAME_SIZE as f32 / SAMPLE_RATE as f32).round() as usize
+            })
+            .collect();
+
+        let mut re = vec![0.0; FRAME_SIZE];
+        let mut im = vec![0.0; FRAME_SIZE];
+        let mut prev: Option<Vec<f32>> = None;
+        let mut hashes = Vec::new();
+        let mut offset = 0;
+        while offset + FRAME_SIZE <= samples.len() {
+            let frame = &samples[offset..offset + FRAME_SIZE];
+            for ((r, x), w) in re.iter_mut().zip(frame).zip(&window) {
+                *r = x * w;
+            }
+            im.fill(0.0);
+            fft(&mut re, &mut im);
+            let energy: Vec<f32> = edges
+                .windows(2)
+                .map(|x| (x[0]..x[1]).map(|k| re[k] * re[k] + im[k] * im[k]).sum())
+                .collect();
+
+            if let Some(prev) = &prev {
+                let mut hash = 0u32;
+                for m in 0..BANDS - 1 {
+                    let diff = (energy[m] - energy[m + 1]) - (prev[m] - prev[m + 1]);
+                    if diff > 0.0 {
+                        hash |= 1 << m;
+                    }
+                }
+                hashes.push(hash);
+            }
+            prev = Some(energy);
+            offset += HOP;
+        }
+        Self { start, hashes }
+    }
+
+    /// 解码视频 `segment` 范围内的音频并计算指纹
+    ///
+    /// Examples
+    ///
+    /// ```no_run
+    /// use bili_video::{Fingerprint, Segment};
+    ///
+    /// let fp = Fingerprint::from_file("examples/data/trailer.mp4", Segment::new(0.0, 300.0)).unwrap();
+    /// assert!(!fp.hashes.is_empty());
+    /// ```
+    pub fn from_file<P: AsRef<Path>>(path: P, segment: Segment) -> Result<Self> {
+        let (samples, start) = decode_pcm(path.as_ref(), segment)?;
+        Ok(Self::from_pcm(&samples, start))
+    }
+}
+
+/// 解码音频为 11025 Hz 单声道采样，返回采样和第一个采样的时间
+fn decode_pcm(path: &Path, segment: Segment) -> Result<(Vec<f32>, f64)> {
+    ffmpeg::init()?;
+    let mut ictx = ffmpeg::format::input(&path)?;
+    let (index, time_base, start_time, parameters) = {
+        let stream = ictx.streams().best(Type::Audio).ok_or(anyhow!("{:?} 没有音频", path))?;
+        let start_time = match stream.start_time() {
+            AV_NOPTS_VALUE => 0,
+            x => x,
+        };
+        (stream.index(), f64::from(stream.time_base()), start_time, stream.parameters())
+    };
+    let mut decoder = ffmpeg::codec::context::Context::from_parameters(parameters)?.decoder().audio()?;
+
+    if segment.start > 0.0 {
+        let ts = (segment.start * f64::from(ffmpeg::ffi::AV_TIME_BASE)) as i64;
+        ictx.seek(ts, ..ts)?;
+    }
+
+    let mut pcm = Pcm { samples: Vec::new(), start: None, segment, resampler: None };
+    for (stream, packet) in ictx.packets() {
+        if stream.index() != index {
+            continue;
+        }
+        // 损坏的包直接跳过
+        if decoder.send_packet(&packet).is_err() {
+            continue;
+        }
+        pcm.receive(&mut decoder, time_base, start_time)?;
+        if pcm.is_full() {
+            break;
+        }
+    }
+    if !pcm.is_full() {
+        decoder.send_eof()?;
+        pcm.receive(&mut decoder, time_base, start_time)?;
+    }
+
+    let start = pcm.start.unwrap_or(segment.start);
+    // seek 只能到达之前的关键帧，丢弃开始之前的采样
+    let skip = (((segment.start - start) * SAMPLE_RATE as f64).max(0.0) as usize).min(pcm.samples.len());
+    let mut samples = pcm.samples;
+    samples.drain(..skip);
+    samples.truncate((segment.duration() * SAMPLE_RATE as f64) as usize);
+    Ok((samples, start.max(segment.start)))
+}
+
+/// 解码过程中的状态
+struct Pcm {
+    samples: Vec<f32>,
+    start: Option<f64>,
+    segment: Segment,
+    resampler: Option<resampling::Context>,
+}
+
+impl Pcm {
+    fn is_full(&self) -> bool {
+        match self.start {
+            Some(start) => start + self.samples.len() as f64 / SAMPLE_RATE as f64 >= self.segment.end,
+            None => false,
+        }
+    }
+
+    fn receive(&mut self, decoder: &mut ffmpeg::decoder::Audio, time_base: f64, start_time: i64) -> Result<()> {
+        let mut decoded = frame::Audio::empty();
+        while decoder.receive_frame(&mut decoded).is_ok() {
+            // 部分封装没有声道布局，按声道数补全
+            if decoded.channel_layout().is_empty() {
+                decoded.set_channel_layout(ChannelLayout::default(decoded.channels() as i32));
+            }
+            if self.start.is_none() {
+                let pts = decoded.pts().unwrap_or(start_time);
+                self.start = Some((pts - start_time) as f64 * time_base);
+            }
+            if self.resampler.is_none() {
+                self.resampler = Some(resampling::Context::get(
+                    decoded.format(),
+                    decoded.channel_layout(),
+                    decoded.rate(),
+                    Sample::F32(sample::Type::Packed),
+                    ChannelLayout::MONO,
+                    SAMPLE_RATE,
+                )?);
+            }
+            let resampler = self.resampler.as_mut().expect("resampler not initialized");
+            let mut resampled = frame::Audio::empty();
+            resampler.run(&decoded, &mut resampled)?;
+            self.samples.extend_from_slice(&resampled.plane::<f32>(0)[..resampled.samples()]);
+        }
+        Ok(())
+    }
+}
+
+/// 原地计算复数 FFT，长度必须是 2 的幂
+fn fft(re: &mut [f32], im: &mut [f32]) {
+    let n = re.len();
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let mut len = 2;
+    while len <= n {
+        let angle = -2.0 * PI / len as f32;
+        let (wr, wi) = (angle.cos(), angle.sin());
+        for start in (0..n).step_by(len) {
+            let (mut cr, mut ci) = (1.0f32, 0.0f32);
+            for k in 0..len / 2 {
+                let a = start + k;
+                let b = a + len / 2;
+                let tr = re[b] * cr - im[b] * ci;
+                let ti = re[b] * ci + im[b] * cr;
+                re[b] = re[a] - tr;
+                im[b] = im[a] - ti;
+                re[a] += tr;
+                im[a] += ti;
+                let next = cr * wr - ci * wi;
+                ci = cr * wi + ci * wr;
+                cr = next;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+/// 找到两个指纹中最长的相同片段，分别返回在两个视频中的时间
+///
+/// 片段短于 `min_duration` 秒时返回 `None`
+pub fn find_common(a: &Fingerprint, b: &Fingerprint, min_duration: f64) -> Option<(Segment, Segment)> {
+    let (n, m) = (a.hashes.len() as isize, b.hashes.len() as isize);
+    // (长度, a 的开始, b 的开始)
+    let mut best: Option<(usize, usize, usize)> = None;
+    for offset in -(m - 1)..n {
+        let (a_from, b_from) = if offset >= 0 { (offset as usize, 0) } else { (0, (-offset) as usize) };
+        let len = (n as usize - a_from).min(m as usize - b_from);
+
+        let mut run: Option<(usize, usize)> = None;
+        let mut gap = 0;
+        for i in 0..=len {
+            let matched = i < len && (a.hashes[a_from + i] ^ b.hashes[b_from + i]).count_ones() <= MAX_BIT_ERRORS;
+            if matched {
+                run = Some(run.map_or((i, i), |(start, _)| (start, i)));
+                gap = 0;
+                continue;
+            }
+            gap += 1;
+            if let Some((start, end)) = run {
+                if gap > MAX_GAP || i == len {
+                    let length = end - start + 1;
+                    if best.map_or(true, |x| length > x.0) {
+                        best = Some((length, a_from + start, b_from + start));
+                    }
+                    run = None;
+                }
+            }
+        }
+    }
+
+    let (length, a_start, b_start) = best?;
+    let duration = length as f64 * Fingerprint::step();
+    if duration < min_duration {
+        return None;
+    }
+    let a_segment = Segment::new(a.time(a_start), a.time(a_start + length - 1));
+    let b_segment = Segment::new(b.time(b_start), b.time(b_start + length - 1));
+    Some((a_segment, b_segment))
+}
+
+/// 一集的匹配结果
+#[derive(Debug, Clone, PartialEq)]
+pub struct EpisodeMatch {
+    pub path: PathBuf,
+    pub duration: f64,
+    pub intro: Option<Segment>,
+    pub outro: Option<Segment>,
+}
+
+impl EpisodeMatch {
+    /// 匹配到的片头片尾，可以直接作为 `exclude_segments`
+    pub fn exclude_segments(&self) -> Vec<Segment> {
+        self.intro.iter().chain(self.outro.iter()).copied().collect()
+    }
+}
+
+/// 在一季的所有剧集之间匹配重复的片头片尾
+///
+/// Examples
+///
+/// ```ignore
+/// use bili_video::SeasonMatcher;
+///
+/// let paths = ["/media/S01E01.mp4", "/media/S01E02.mp4", "/media/S01E03.mp4"];
+/// for m in SeasonMatcher::default().run(&paths).unwrap() {
+///     println!("{:?} {:?}", m.path, m.exclude_segments());
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SeasonMatcher {
+    /// 在开头多少秒内查找片头
+    pub intro_window: f64,
+    /// 在结尾多少秒内查找片尾
+    pub outro_window: f64,
+    /// 片头片尾的最短时长
+    pub min_duration: f64,
+    /// 离视频开头或结尾不超过该值时对齐到开头或结尾
+    pub edge: f64,
+}
+
+impl Default for SeasonMatcher {
+    fn default() -> Self {
+        Self { intro_window: 300.0, outro_window: 300.0, min_duration: 20.0, edge: 5.0 }
+    }
+}
+
+impl SeasonMatcher {
+    /// 每一集和前后两集比较，取最长的相同片段
+    pub fn run<P: AsRef<Path>>(&self, paths: &[P]) -> Result<Vec<EpisodeMatch>> {
+        if paths.len() < 2 {
+            return Err(anyhow!("至少需要两集才能匹配片头片尾"));
+        }
+
+        let mut episodes = Vec::new();
+        for path in paths {
+            let path = path.as_ref();
+            let duration = executor().probe(path)?.duration;
+            episodes.push(Prints {
+                path: path.to_path_buf(),
+                duration,
+                intro: Fingerprint::from_file(path, Segment::new(0.0, self.intro_window.min(duration)))?,
+                outro: Fingerprint::from_file(path, Segment::new((duration - self.outro_window).max(0.0), duration))?,
+            });
+        }
+
+        let matches = episodes
+            .iter()
+            .enumerate()
+            .map(|(i, episode)| {
+                let neighbours: Vec<&Prints> = [i.checked_sub(1), Some(i + 1)]
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|j| episodes.get(j))
+                    .collect();
+                let intro = self.longest(neighbours.iter().map(|x| (&episode.intro, &x.intro)));
+                let outro = self.longest(neighbours.iter().map(|x| (&episode.outro, &x.outro)));
+                EpisodeMatch {
+                    path: episode.path.clone(),
+                    duration: episode.duration,
+                    intro: intro.map(|x| self.snap(x, episode.duration)),
+                    outro: outro.map(|x| self.snap(x, episode.duration)),
+                }
+            })
+            .collect();
+        Ok(matches)
+    }
+
+    /// 所有配对中最长的相同片段，返回在第一个指纹中的时间
+    fn longest<'a, I>(&self, pairs: I) -> Option<Segment>
+    where
+        I: Iterator<Item = (&'a Fingerprint, &'a Fingerprint)>,
+    {
+        pairs
+            .filter_map(|(a, b)| find_common(a, b, self.min_duration))
+            .map(|(x, _)| x)
+            .max_by(|a, b| a.duration().total_cmp(&b.duration()))
+    }
+
+    /// 靠近开头或结尾的边界对齐到开头或结尾
+    fn snap(&self, segment: Segment, duration: f64) -> Segment {
+        let start = if segment.start <= self.edge { 0.0 } else { segment.start };
+        let end = if duration - segment.end <= self.edge { duration } else { segment.end };
+        Segment::new(start, end)
+    }
+}
+
+/// 一集开头和结尾的指纹
+struct Prints {
+    path: PathBuf,
+    duration: f64,
+    intro: Fingerprint,
+    outro: Fingerprint,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fft, find_common, Fingerprint, FRAME_SIZE, HOP};
+
+    /// 可重复的伪随机噪声
+    fn noise(seed: u64, len: usize) -> Vec<f32> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+                ((state >> 33) as f32 / (1u64 << 31) as f32) - 0.5
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_fft() {
+        let mut re: Vec<f32> = (0..FRAME_SIZE)
+            .map(|i| (2.0 * std::f32::consts::PI * 10.0 * i as f32 / FRAME_SIZE as f32).sin())
+            .collect();
+        let mut im = vec![0.0; FRAME_SIZE];
+        fft(&mut re, &mut im);
+        let peak = (0..FRAME_SIZE / 2)
+            .max_by(|&a, &b| (re[a].hypot(im[a])).total_cmp(&re[b].hypot(im[b])))
+            .unwrap();
+        assert_eq!(peak, 10);
+    }
+
+    #[test]
+    fn test_find_common() {
+        // 相同的片头出现在两集的不同位置
+        let intro = noise(1, 400 * HOP);
+        let a = [noise(2, 200 * HOP), intro.clone(), noise(3, 150 * HOP)].concat();
+        let b = [noise(4, 350 * HOP), intro, noise(5, 100 * HOP)].concat();
+        let a = Fingerprint::from_pcm(&a, 0.0);
+        let b = Fingerprint::from_pcm(&b, 60.0);
+
+        let step = Fingerprint::step();
+        let (x, y) = find_common(&a, &b, 20.0).unwrap();
+        assert!((x.start - 200.0 * step).abs() < 1.5, "{:?}", x);
+        assert!((x.end - 600.0 * step).abs() < 1.5, "{:?}", x);
+        assert!((y.start - 60.0 - 350.0 * step).abs() < 1.5, "{:?}", y);
+        assert!((x.duration() - y.duration()).abs() < 1e-9);
+
+        // 没有相同片段
+        let c = Fingerprint::from_pcm(&noise(6, 300 * HOP), 0.0);
+        assert!(find_common(&a, &c, 20.0).is_none());
+    }
+}
@@ -0,0 +1,69 @@
+extern crate ffmpeg_next as ffmpeg;
+
+use std::path::Path;
+
+use ffmpeg::{ffi::AV_NOPTS_VALUE, media::Type};
+
+/// 读取视频流所有关键帧的时间，单位为秒，从小到大排列
+///
+/// 时间相对于视频开头，和 `-ss` 使用的时间一致
+///
+/// Examples
+///
+/// ```no_run
+/// use bili_video::probe_keyframes;
+///
+/// let keyframes = probe_keyframes("examples/data/trailer.mp4").unwrap();
+/// assert_eq!(keyframes[0], 0.0);
+/// ```
+pub fn probe_keyframes<P: AsRef<Path>>(path: P) -> Result<Vec<f64>, ffmpeg::Error> {
+    ffmpeg::init()?;
+    let mut ictx = ffmpeg::format::input(&path)?;
+
+    let (index, time_base, start) = {
+        let stream = ictx.streams().best(Type::Video).ok_or(ffmpeg::Error::StreamNotFound)?;
+        let start = match stream.start_time() {
+            AV_NOPTS_VALUE => 0,
+            x => x,
+        };
+        (stream.index(), f64::from(stream.time_base()), start)
+    };
+
+    let mut keyframes = Vec::new();
+    for (stream, packet) in ictx.packets() {
+        if stream.index() != index || !packet.is_key() {
+            continue;
+        }
+        if let Some(pts) = packet.pts() {
+            keyframes.push(round_ms((pts - start) as f64 * time_base));
+        }
+    }
+    keyframes.sort_by(|a, b| a.total_cmp(b));
+    keyframes.dedup();
+    Ok(keyframes)
+}
+
+/// 找到离 `time` 最近的关键帧，没有关键帧时返回 `time`
+///
+/// Examples
+///
+/// ```
+/// use bili_video::snap_to_keyframe;
+///
+/// let keyframes = [0.0, 4.2, 8.4, 12.6];
+/// assert_eq!(snap_to_keyframe(5.0, &keyframes), 4.2);
+/// assert_eq!(snap_to_keyframe(7.0, &keyframes), 8.4);
+/// assert_eq!(snap_to_keyframe(100.0, &keyframes), 12.6);
+/// assert_eq!(snap_to_keyframe(5.0, &[]), 5.0);
+/// ```
+pub fn snap_to_keyframe(time: f64, keyframes: &[f64]) -> f64 {
+    keyframes
+        .iter()
+        .copied()
+        .min_by(|a, b| (a - time).abs().total_cmp(&(b - time).abs()))
+        .unwrap_or(time)
+}
+
+fn round_ms(time: f64) -> f64 {
+    (time * 1000.0).round() / 1000.0
+}
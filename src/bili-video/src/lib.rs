@@ -1,14 +1,122 @@
 mod models;
 mod ffmpeg;
+mod command;
+mod executor;
+mod keyframe;
+mod cutter;
+mod profile;
+mod twopass;
+mod loudnorm;
+mod detect;
+mod fingerprint;
+mod boundary;
 mod spliter;
 mod remover;
+mod progress;
+mod process;
+#[cfg(test)]
+mod testing;
 
-pub use models::Video;
+pub use models::{
+    Video,
+    Segment,
+    SegmentError,
+    Timestamp,
+    check_segments,
+    check_duration,
+    clamp_segments,
+    normalize_segments,
+    AudioStream,
+    SubtitleStream,
+    Chapter,
+};
 pub use spliter::{
     Spliter,
+    SplitBy,
     split,
 };
 pub use remover::Remover;
+pub use keyframe::{
+    probe_keyframes,
+    snap_to_keyframe,
+};
+pub use cutter::{
+    Cut,
+    CutMode,
+    plan_cuts,
+};
+pub use executor::{
+    Executor,
+    SystemExecutor,
+    RecordingExecutor,
+    DryRunExecutor,
+    executor,
+    set_executor,
+    with_executor,
+    shell_join,
+};
+pub use profile::{
+    TranscodeProfile,
+    ProfilePatch,
+    TranscodePlan,
+    resolve_profile,
+    StreamAction,
+    transcode,
+    transcode_with_plan,
+};
+pub use twopass::{
+    target_bitrate,
+    transcode_to_size,
+};
+pub use loudnorm::{
+    Loudnorm,
+    Loudness,
+    LoudnessError,
+    measure_loudness,
+    parse_loudness,
+    normalize_loudness,
+};
+pub use detect::{
+    Detector,
+    Detection,
+    Boundary,
+    parse_silence,
+    parse_black,
+};
+pub use fingerprint::{
+    Fingerprint,
+    EpisodeMatch,
+    SeasonMatcher,
+    find_common,
+};
+pub use boundary::{
+    BoundaryMode,
+    find_boundary,
+    parse_scene,
+};
+pub use command::{
+    FfmpegCommand,
+    Input,
+    Output,
+};
+pub use progress::{
+    Progress,
+    ProgressHandler,
+    ProgressParser,
+    set_progress_handler,
+    clear_progress_handler,
+    progress_channel,
+};
+pub use process::{
+    CancelToken,
+    ProcessError,
+    cancel_token,
+    current_cancel_token,
+    with_cancel_token,
+    set_step_timeout,
+    step_timeout,
+    spawn,
+};
 pub use ffmpeg::{
     to_ts,
     to_m3u8,
@@ -21,4 +129,6 @@ pub use ffmpeg::{
     screenshot,
     transcode_1080,
     float_to_time_format,
+    format_timestamp,
+    parse_timestamp,
 };
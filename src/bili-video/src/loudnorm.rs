@@ -0,0 +1,215 @@
+use std::{collections::HashMap, error::Error, fmt, path::Path};
+
+use anyhow::{anyhow, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{FfmpegCommand, TranscodeProfile};
+
+/// EBU R128 响度标准化参数
+///
+/// 在 TOML 中写作 `loudnorm = { i = -16.0, tp = -1.5, lra = 11.0 }`，未填写的字段使用默认值
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct Loudnorm {
+    /// 目标综合响度（LUFS）
+    pub i: f64,
+    /// 最大真峰值（dBTP）
+    pub tp: f64,
+    /// 目标响度范围（LU）
+    pub lra: f64,
+}
+
+impl Default for Loudnorm {
+    fn default() -> Self {
+        Self { i: -16.0, tp: -1.5, lra: 11.0 }
+    }
+}
+
+/// 无法测量响度的原因，这类文件跳过标准化即可
+#[derive(Debug, Clone, PartialEq)]
+pub enum LoudnessError {
+    /// 没有输出测量结果，通常是没有音频
+    NoAudio,
+    /// 测量值为 -inf，音频是静音
+    Silent,
+}
+
+impl fmt::Display for LoudnessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoudnessError::NoAudio => write!(f, "loudnorm 没有输出测量结果"),
+            LoudnessError::Silent => write!(f, "loudnorm 测量值为 -inf，音频可能是静音"),
+        }
+    }
+}
+
+impl Error for LoudnessError {}
+
+/// 第一遍测量得到的响度
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Loudness {
+    pub input_i: f64,
+    pub input_tp: f64,
+    pub input_lra: f64,
+    pub input_thresh: f64,
+    pub target_offset: f64,
+}
+
+impl Loudnorm {
+    /// 第一遍测量使用的滤镜
+    pub fn measure_filter(&self) -> String {
+        format!("loudnorm=I={}:TP={}:LRA={}:print_format=json", self.i, self.tp, self.lra)
+    }
+
+    /// 第二遍使用测量结果线性调整音量的滤镜
+    ///
+    /// Examples
+    ///
+    /// ```
+    /// use bili_video::{Loudness, Loudnorm};
+    ///
+    /// let m = Loudness {
+    ///     input_i: -27.61,
+    ///     input_tp: -4.47,
+    ///     input_lra: 18.06,
+    ///     input_thresh: -39.2,
+    ///     target_offset: 0.58,
+    /// };
+    /// assert_eq!(
+    ///     Loudnorm::default().filter(&m),
+    ///     "loudnorm=I=-16:TP=-1.5:LRA=11:measured_I=-27.61:measured_TP=-4.47:\
+    ///      measured_LRA=18.06:measured_thresh=-39.2:offset=0.58:linear=true:print_format=summary",
+    /// );
+    /// ```
+    pub fn filter(&self, m: &Loudness) -> String {
+        format!(
+            "loudnorm=I={}:TP={}:LRA={}:measured_I={}:measured_TP={}:measured_LRA={}:measured_thresh={}:offset={}:linear=true:print_format=summary",
+            self.i, self.tp, self.lra, m.input_i, m.input_tp, m.input_lra, m.input_thresh, m.target_offset,
+        )
+    }
+
+    /// 响度已经在目标 1 LU 以内并且没有超过真峰值
+    pub fn is_compliant(&self, m: &Loudness) -> bool {
+        (m.input_i - self.i).abs() <= 1.0 && m.input_tp <= self.tp
+    }
+}
+
+/// 测量音频响度
+///
+/// Examples
+///
+/// ```ignore
+/// use bili_video::{measure_loudness, Loudnorm};
+///
+/// let m = measure_loudness("/tmp/test.mp4", &Loudnorm::default()).unwrap();
+/// println!("{} LUFS", m.input_i);
+/// ```
+pub fn measure_loudness<P: AsRef<Path>>(path: P, loudnorm: &Loudnorm) -> Result<Loudness> {
+    let null = if cfg!(windows) { "NUL" } else { "/dev/null" };
+    let mut cmd = FfmpegCommand::new();
+    cmd.input(&path);
+    cmd.output(null)
+        .map("0:a:0")
+        .audio_filter(&loudnorm.measure_filter())
+        .format("null");
+    let stderr = cmd.analyze()?;
+    parse_loudness(&stderr)
+}
+
+/// 解析 `loudnorm` 输出的 JSON
+///
+/// Examples
+///
+/// ```
+/// use bili_video::{parse_loudness, LoudnessError};
+///
+/// let stderr = r#"
+/// [Parsed_loudnorm_0 @ 0x7f8b5c004a80]
+/// {
+///     "input_i" : "-27.61",
+///     "input_tp" : "-4.47",
+///     "input_lra" : "18.06",
+///     "input_thresh" : "-39.20",
+///     "output_i" : "-16.58",
+///     "normalization_type" : "dynamic",
+///     "target_offset" : "0.58"
+/// }
+/// "#;
+/// let m = parse_loudness(stderr).unwrap();
+/// assert_eq!(m.input_i, -27.61);
+/// assert_eq!(m.target_offset, 0.58);
+///
+/// let err = parse_loudness("no audio").unwrap_err();
+/// assert_eq!(err.downcast_ref::<LoudnessError>(), Some(&LoudnessError::NoAudio));
+/// ```
+pub fn parse_loudness(stderr: &str) -> Result<Loudness> {
+    let start = stderr.rfind("[Parsed_loudnorm").ok_or(LoudnessError::NoAudio)?;
+    let body = &stderr[start..];
+    let (open, close) = match (body.find('{'), body.find('}')) {
+        (Some(open), Some(close)) if open < close => (open, close),
+        _ => return Err(anyhow!("loudnorm 输出格式错误")),
+    };
+    let values: HashMap<String, String> = serde_json::from_str(&body[open..=close])?;
+    let get = |key: &str| -> Result<f64> {
+        let value = values.get(key).ok_or(anyhow!("loudnorm 缺少 {}", key))?;
+        let value: f64 = value.trim().parse().map_err(|_| anyhow!("loudnorm {} 不是数字: {}", key, value))?;
+        // 静音时测量值为 -inf，无法标准化
+        if !value.is_finite() {
+            return Err(LoudnessError::Silent.into());
+        }
+        Ok(value)
+    };
+    Ok(Loudness {
+        input_i: get("input_i")?,
+        input_tp: get("input_tp")?,
+        input_lra: get("input_lra")?,
+        input_thresh: get("input_thresh")?,
+        target_offset: get("target_offset")?,
+    })
+}
+
+/// 两遍响度标准化，视频直接复制，音频使用 `profile` 中的音频参数重新编码
+///
+/// 已经符合目标响度时返回 `false` 并且不生成文件
+///
+/// Examples
+///
+/// ```ignore
+/// use bili_video::{normalize_loudness, Loudnorm, TranscodeProfile};
+///
+/// let profile = TranscodeProfile::default();
+/// normalize_loudness("/tmp/part.ts", "/tmp/part.loudnorm.ts", &Loudnorm::default(), &profile).unwrap();
+/// ```
+pub fn normalize_loudness<F, T>(from: F, to: T, loudnorm: &Loudnorm, profile: &TranscodeProfile) -> Result<bool>
+where
+    F: AsRef<Path>,
+    T: AsRef<Path>,
+{
+    let m = measure_loudness(&from, loudnorm)?;
+    if loudnorm.is_compliant(&m) {
+        return Ok(false);
+    }
+
+    let mut cmd = FfmpegCommand::new();
+    cmd.overwrite().input(&from);
+    let output = cmd.output(&to);
+    output.video_codec("copy");
+    profile.apply_audio(output).audio_filter(&loudnorm.filter(&m));
+    cmd.run()?;
+    Ok(true)
+}
+
+/// 转码配置设置了响度标准化时，测量源文件并返回第二遍的滤镜
+///
+/// 没有音频或静音时无法标准化，不使用滤镜
+pub(crate) fn loudnorm_filter(from: &Path, profile: &TranscodeProfile) -> Result<Option<String>> {
+    let Some(loudnorm) = &profile.loudnorm else {
+        return Ok(None);
+    };
+    match measure_loudness(from, loudnorm) {
+        Ok(m) => Ok(Some(loudnorm.filter(&m))),
+        Err(e) if e.downcast_ref::<LoudnessError>().is_some() => Ok(None),
+        Err(e) => Err(e),
+    }
+}
@@ -1,3 +1,7 @@
 mod video;
+mod segment;
+mod stream;
 
 pub use video::Video;
+pub use segment::{check_duration, check_segments, clamp_segments, normalize_segments, Segment, SegmentError, Timestamp};
+pub use stream::{AudioStream, SubtitleStream, Chapter};
@@ -0,0 +1,354 @@
+use std::fmt;
+
+use schemars::{gen::SchemaGenerator, schema::Schema, JsonSchema};
+use serde::{de, ser::SerializeTuple, Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::json;
+
+use crate::{format_timestamp, parse_timestamp};
+
+/// 视频片段，单位为秒，精确到毫秒
+///
+/// 在 TOML 中写作 `[开始, 结束]`，时间可以是秒数或 `hh:mm:ss.mmm` 字符串
+///
+/// ```toml
+/// exclude_segments = [[0, 89.4], ["00:42:10", "00:43:05.500"]]
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Segment {
+    pub start: f64,
+    pub end: f64,
+}
+
+impl Segment {
+    pub fn new(start: f64, end: f64) -> Self {
+        Self { start, end }
+    }
+
+    /// 片段时长
+    pub fn duration(&self) -> f64 {
+        self.end - self.start
+    }
+
+    /// 去掉和 `others` 重叠的部分，返回剩余的片段
+    ///
+    /// Examples
+    ///
+    /// ```
+    /// use bili_video::Segment;
+    ///
+    /// let s = Segment::new(0.0, 100.0);
+    /// assert_eq!(s.subtract(&[Segment::new(20.0, 30.0), Segment::new(90.0, 120.0)]), vec![
+    ///     Segment::new(0.0, 20.0),
+    ///     Segment::new(30.0, 90.0),
+    /// ]);
+    /// assert!(s.subtract(&[Segment::new(0.0, 100.0)]).is_empty());
+    /// ```
+    pub fn subtract(&self, others: &[Segment]) -> Vec<Segment> {
+        let mut others: Vec<Segment> = others.to_vec();
+        others.sort_by(|a, b| a.start.total_cmp(&b.start));
+
+        let mut remaining = Vec::new();
+        let mut start = self.start;
+        for other in others {
+            if other.end <= start || other.start >= self.end {
+                continue;
+            }
+            if other.start > start {
+                remaining.push(Segment::new(start, other.start));
+            }
+            start = start.max(other.end);
+        }
+        if start < self.end {
+            remaining.push(Segment::new(start, self.end));
+        }
+        remaining
+    }
+}
+
+/// 片段列表中的问题
+///
+/// `Overlap` 和 `Unsorted` 可以由 [`normalize_segments`] 自动修正，其余的会被拒绝
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SegmentError {
+    /// 时间不是有效的非负数
+    Invalid(Segment),
+    /// 结束时间不晚于开始时间
+    Reversed(Segment),
+    /// 开始时间超出视频时长，截取时丢弃该片段，由 [`check_duration`] 列出
+    OutOfRange(Segment, f64),
+    /// 和前一个片段重叠
+    Overlap(Segment, Segment),
+    /// 开始时间早于前一个片段
+    Unsorted(Segment),
+}
+
+impl SegmentError {
+    /// 是否可以通过排序合并修正
+    pub fn is_fixable(&self) -> bool {
+        matches!(self, Self::Overlap(..) | Self::Unsorted(..))
+    }
+}
+
+impl fmt::Display for SegmentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Invalid(x) => write!(f, "片段 [{}, {}] 的时间无效", x.start, x.end),
+            Self::Reversed(x) => write!(f, "片段 {} 的结束时间不晚于开始时间", x),
+            Self::OutOfRange(x, duration) => {
+                write!(f, "片段 {} 超出视频时长 {}", x, format_timestamp(*duration))
+            }
+            Self::Overlap(a, b) => write!(f, "片段 {} 和 {} 重叠", a, b),
+            Self::Unsorted(x) => write!(f, "片段 {} 早于前一个片段", x),
+        }
+    }
+}
+
+impl std::error::Error for SegmentError {}
+
+impl Segment {
+    /// 检查单个片段，时间必须是非负数且结束晚于开始
+    ///
+    /// Examples
+    ///
+    /// ```
+    /// use bili_video::{Segment, SegmentError};
+    ///
+    /// assert!(Segment::new(0.0, 89.4).validate().is_ok());
+    /// assert_eq!(Segment::new(90.0, 10.0).validate(), Err(SegmentError::Reversed(Segment::new(90.0, 10.0))));
+    /// assert_eq!(Segment::new(-1.0, 10.0).validate(), Err(SegmentError::Invalid(Segment::new(-1.0, 10.0))));
+    /// ```
+    pub fn validate(&self) -> Result<(), SegmentError> {
+        if !self.start.is_finite() || !self.end.is_finite() || self.start < 0.0 {
+            return Err(SegmentError::Invalid(*self));
+        }
+        if self.end <= self.start {
+            return Err(SegmentError::Reversed(*self));
+        }
+        Ok(())
+    }
+}
+
+/// 列出片段列表中的所有问题，包括可以自动修正的乱序和重叠
+///
+/// Examples
+///
+/// ```
+/// use bili_video::{check_segments, Segment, SegmentError};
+///
+/// let s = |a: f64, b: f64| Segment::new(a, b);
+///
+/// assert!(check_segments(&[s(0.0, 90.0), s(2600.0, 2700.0)]).is_empty());
+/// assert_eq!(check_segments(&[s(0.0, 90.0), s(80.0, 100.0), s(50.0, 40.0)]), vec![
+///     SegmentError::Reversed(s(50.0, 40.0)),
+///     SegmentError::Overlap(s(0.0, 90.0), s(80.0, 100.0)),
+///     SegmentError::Unsorted(s(50.0, 40.0)),
+/// ]);
+/// ```
+pub fn check_segments(segments: &[Segment]) -> Vec<SegmentError> {
+    let mut errors: Vec<SegmentError> = segments.iter().filter_map(|x| x.validate().err()).collect();
+    for pair in segments.windows(2) {
+        let (a, b) = (pair[0], pair[1]);
+        if b.start < a.start {
+            errors.push(SegmentError::Unsorted(b));
+        } else if b.start < a.end {
+            errors.push(SegmentError::Overlap(a, b));
+        }
+    }
+    errors
+}
+
+/// 列出开始时间超出视频时长的片段，这些片段在截取时会被丢弃
+///
+/// Examples
+///
+/// ```
+/// use bili_video::{check_duration, Segment, SegmentError};
+///
+/// let s = |a: f64, b: f64| Segment::new(a, b);
+///
+/// assert!(check_duration(&[s(0.0, 90.0), s(900.0, 1100.0)], 1000.0).is_empty());
+/// assert_eq!(check_duration(&[s(0.0, 90.0), s(1200.0, 1300.0)], 1000.0), vec![
+///     SegmentError::OutOfRange(s(1200.0, 1300.0), 1000.0),
+/// ]);
+/// ```
+pub fn check_duration(segments: &[Segment], duration: f64) -> Vec<SegmentError> {
+    segments
+        .iter()
+        .filter(|x| x.start >= duration)
+        .map(|x| SegmentError::OutOfRange(*x, duration))
+        .collect()
+}
+
+/// 排序并合并重叠的片段，结束时间截到视频时长
+///
+/// 整季共用的片段可能超出较短的一集，开始超出视频时长的片段会被丢弃，可以用 [`check_duration`] 列出；
+/// 片段无效或结束不晚于开始时返回错误
+///
+/// Examples
+///
+/// ```
+/// use bili_video::{normalize_segments, Segment, SegmentError};
+///
+/// let s = |a: f64, b: f64| Segment::new(a, b);
+///
+/// let segments = normalize_segments(&[s(800.0, 1100.0), s(0.0, 90.0), s(60.0, 120.0)], 1000.0).unwrap();
+/// assert_eq!(segments, vec![s(0.0, 120.0), s(800.0, 1000.0)]);
+///
+/// let segments = normalize_segments(&[s(0.0, 90.0), s(1200.0, 1300.0)], 1000.0).unwrap();
+/// assert_eq!(segments, vec![s(0.0, 90.0)]);
+///
+/// let err = normalize_segments(&[s(90.0, 0.0)], 1000.0).unwrap_err();
+/// assert_eq!(err, SegmentError::Reversed(s(90.0, 0.0)));
+/// ```
+pub fn normalize_segments(segments: &[Segment], duration: f64) -> Result<Vec<Segment>, SegmentError> {
+    let mut sorted = clamp_segments(segments, duration)?;
+    sorted.sort_by(|a, b| a.start.total_cmp(&b.start));
+
+    let mut merged: Vec<Segment> = Vec::new();
+    for segment in sorted {
+        match merged.last_mut() {
+            Some(last) if segment.start <= last.end => last.end = last.end.max(segment.end),
+            _ => merged.push(segment),
+        }
+    }
+    Ok(merged)
+}
+
+/// 检查片段并截到视频时长，顺序不变，开始超出视频时长的片段被丢弃
+///
+/// Examples
+///
+/// ```
+/// use bili_video::{clamp_segments, Segment};
+///
+/// let s = |a: f64, b: f64| Segment::new(a, b);
+///
+/// let segments = clamp_segments(&[s(600.0, 700.0), s(900.0, 1100.0), s(1200.0, 1300.0)], 1000.0).unwrap();
+/// assert_eq!(segments, vec![s(600.0, 700.0), s(900.0, 1000.0)]);
+/// ```
+pub fn clamp_segments(segments: &[Segment], duration: f64) -> Result<Vec<Segment>, SegmentError> {
+    let mut clamped = Vec::new();
+    for segment in segments {
+        segment.validate()?;
+        if segment.start >= duration {
+            continue;
+        }
+        clamped.push(Segment::new(segment.start, segment.end.min(duration)));
+    }
+    Ok(clamped)
+}
+
+impl From<(f64, f64)> for Segment {
+    fn from((start, end): (f64, f64)) -> Self {
+        Self::new(start, end)
+    }
+}
+
+impl From<(u64, u64)> for Segment {
+    fn from((start, end): (u64, u64)) -> Self {
+        Self::new(start as f64, end as f64)
+    }
+}
+
+impl fmt::Display for Segment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", format_timestamp(self.start), format_timestamp(self.end))
+    }
+}
+
+impl Serialize for Segment {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut tuple = serializer.serialize_tuple(2)?;
+        tuple.serialize_element(&self.start)?;
+        tuple.serialize_element(&self.end)?;
+        tuple.end()
+    }
+}
+
+impl JsonSchema for Segment {
+    fn schema_name() -> String {
+        "Segment".to_string()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        let time = serde_json::to_value(gen.subschema_for::<Timestamp>()).expect("invalid schema");
+        let schema = json!({
+            "description": "片段 [开始, 结束]，时间可以是秒数或 hh:mm:ss.mmm",
+            "type": "array",
+            "items": time,
+            "minItems": 2,
+            "maxItems": 2,
+        });
+        serde_json::from_value(schema).expect("invalid schema")
+    }
+}
+
+/// 秒数或时间字符串
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TimeValue {
+    Seconds(f64),
+    Text(String),
+}
+
+impl TimeValue {
+    fn to_seconds<E: de::Error>(&self) -> Result<f64, E> {
+        match self {
+            TimeValue::Seconds(s) => Ok(*s),
+            TimeValue::Text(s) => parse_timestamp(s).map_err(E::custom),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Segment {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let (start, end) = <(TimeValue, TimeValue)>::deserialize(deserializer)?;
+        Ok(Self::new(start.to_seconds()?, end.to_seconds()?))
+    }
+}
+
+/// 配置中的时间点，单位为秒
+///
+/// 在 TOML 中可以写秒数或 `hh:mm:ss.mmm` 字符串
+///
+/// ```toml
+/// max_duration = "00:08:00"
+/// split_points = [600, "00:21:30.500"]
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd)]
+pub struct Timestamp(pub f64);
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", format_timestamp(self.0))
+    }
+}
+
+impl Serialize for Timestamp {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Timestamp {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Self(TimeValue::deserialize(deserializer)?.to_seconds()?))
+    }
+}
+
+impl JsonSchema for Timestamp {
+    fn schema_name() -> String {
+        "Timestamp".to_string()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        let schema = json!({
+            "description": "秒数或 hh:mm:ss.mmm",
+            "anyOf": [
+                { "type": "number", "minimum": 0 },
+                { "type": "string", "pattern": "^(\\d+:)?(\\d+:)?\\d+(\\.\\d+)?$" },
+            ],
+        });
+        serde_json::from_value(schema).expect("invalid schema")
+    }
+}
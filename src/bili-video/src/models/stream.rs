@@ -0,0 +1,29 @@
+use serde::{Deserialize, Serialize};
+
+/// 音频流信息
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AudioStream {
+    pub index: usize,
+    pub codec: String,
+    pub channels: u16,
+    pub sample_rate: u32,
+    pub bit_rate: Option<u64>,
+    pub language: Option<String>,
+}
+
+/// 字幕流信息
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SubtitleStream {
+    pub index: usize,
+    pub codec: String,
+    pub language: Option<String>,
+    pub title: Option<String>,
+}
+
+/// 章节，时间单位为秒
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Chapter {
+    pub start: f64,
+    pub end: f64,
+    pub title: Option<String>,
+}
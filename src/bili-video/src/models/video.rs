@@ -1,7 +1,11 @@
 extern crate ffmpeg_next as ffmpeg;
 use serde::{Serialize, Deserialize};
 
-use std::{error::Error, fmt, fs, path::Path};
+use std::{collections::BTreeMap, error::Error, ffi::CStr, fmt, fs, path::Path};
+
+use ffmpeg::{codec::packet::side_data, format::stream::Stream, media::Type};
+
+use super::{AudioStream, Chapter, SubtitleStream};
 
 #[derive(Debug)]
 /// 定义视频的错误类型
@@ -15,7 +19,7 @@ impl fmt::Display for VideoError {
 
 impl Error for VideoError {}
 
-#[derive(Debug, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Video {
     pub width: u32,
     pub height: u32,
@@ -23,6 +27,48 @@ pub struct Video {
     pub duration: f64,
     pub format: Option<String>,
     pub path: String,
+    /// 视频编码，如 `h264`、`hevc`
+    #[serde(default)]
+    pub codec: Option<String>,
+    /// 编码档次，如 `High`、`Main 10`
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// 编码级别，如 h264 的 `41` 表示 4.1
+    #[serde(default)]
+    pub level: Option<i32>,
+    /// 平均帧率
+    #[serde(default)]
+    pub fps: Option<f64>,
+    /// 平均帧率的分数形式，如 `24000/1001`
+    #[serde(default)]
+    pub frame_rate: Option<String>,
+    /// 视频流的时间基，如 `1/12800`
+    #[serde(default)]
+    pub time_base: Option<String>,
+    /// 视频流码率，没有时使用容器的总码率
+    #[serde(default)]
+    pub bit_rate: Option<u64>,
+    /// 像素宽高比，如 `1:1`
+    #[serde(default)]
+    pub sar: Option<String>,
+    /// 显示宽高比，如 `16:9`
+    #[serde(default)]
+    pub dar: Option<String>,
+    /// 逆时针旋转角度
+    #[serde(default)]
+    pub rotation: Option<f64>,
+    /// 色彩传输特性，如 `smpte2084`、`arib-std-b67`
+    #[serde(default)]
+    pub color_transfer: Option<String>,
+    #[serde(default)]
+    pub audios: Vec<AudioStream>,
+    #[serde(default)]
+    pub subtitles: Vec<SubtitleStream>,
+    #[serde(default)]
+    pub chapters: Vec<Chapter>,
+    /// 容器的元数据
+    #[serde(default)]
+    pub tags: BTreeMap<String, String>,
 }
 
 impl fmt::Display for Video {
@@ -65,17 +111,31 @@ impl Video {
         Ok(video)
     }
 
+    /// 是否为 HDR 视频（PQ 或 HLG）
+    ///
+    /// Examples
+    ///
+    /// ```
+    /// use bili_video::Video;
+    ///
+    /// let video = Video { color_transfer: Some("smpte2084".into()), ..Default::default() };
+    /// assert!(video.is_hdr());
+    /// assert!(!Video::default().is_hdr());
+    /// ```
+    pub fn is_hdr(&self) -> bool {
+        matches!(self.color_transfer.as_deref(), Some("smpte2084" | "arib-std-b67"))
+    }
+
     /// 通过 ffmpeg 获取视频信息
     fn by_ffmpeg<P: AsRef<Path>>(file: P) -> Result<Video, ffmpeg::Error> {
         use ffmpeg::error::Error::Other as OtherErr;
-        use ffmpeg::media::Type::Video as TypeVideo;
         ffmpeg::init()?;
         let content = ffmpeg::format::input(&file)?;
         // 如果没有视频流直接报错
-        if let Some(_stream) = content.streams().best(TypeVideo) {
-        } else {
-            return Err(OtherErr { errno: 0 });
-        }
+        let best = match content.streams().best(Type::Video) {
+            Some(stream) => stream.index(),
+            None => return Err(OtherErr { errno: 0 }),
+        };
         let mut video = Video::default();
 
         for stream in content.streams() {
@@ -85,18 +145,177 @@ impl Video {
                 video.duration = dur;
             }
 
-            // 获取视频基本信息
-            let codec = ffmpeg::codec::context::Context::from_parameters(stream.parameters())?;
-            if codec.medium() == TypeVideo {
-                if let Ok(v) = codec.decoder().video() {
-                    video.width = v.width();
-                    video.height = v.height();
-                    let format = v.format().descriptor().unwrap().name();
-                    video.format = Some(format.to_string());
+            let codec = stream.parameters().id().name().to_string();
+            let metadata = stream.metadata();
+            let language = metadata.get("language").map(String::from);
+            match stream.parameters().medium() {
+                Type::Video if stream.index() == best => video.read_video_stream(&stream)?,
+                Type::Audio => {
+                    let context = ffmpeg::codec::context::Context::from_parameters(stream.parameters())?;
+                    let (channels, sample_rate) = match context.decoder().audio() {
+                        Ok(audio) => (audio.channels(), audio.rate()),
+                        Err(_) => (0, 0),
+                    };
+                    video.audios.push(AudioStream {
+                        index: stream.index(),
+                        codec,
+                        channels,
+                        sample_rate,
+                        bit_rate: stream_bit_rate(&stream),
+                        language,
+                    });
                 }
+                Type::Subtitle => video.subtitles.push(SubtitleStream {
+                    index: stream.index(),
+                    codec,
+                    language,
+                    title: metadata.get("title").map(String::from),
+                }),
+                _ => {}
             }
         }
 
+        if video.bit_rate.is_none() && content.bit_rate() > 0 {
+            video.bit_rate = Some(content.bit_rate() as u64);
+        }
+
+        for chapter in content.chapters() {
+            let time_base = f64::from(chapter.time_base());
+            video.chapters.push(Chapter {
+                start: chapter.start() as f64 * time_base,
+                end: chapter.end() as f64 * time_base,
+                title: chapter.metadata().get("title").map(String::from),
+            });
+        }
+
+        video.tags = content
+            .metadata()
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+
         Ok(video)
     }
+
+    /// 读取主视频流的信息
+    fn read_video_stream(&mut self, stream: &Stream) -> Result<(), ffmpeg::Error> {
+        self.codec = Some(stream.parameters().id().name().to_string());
+        self.profile = profile_name(stream);
+        self.level = level(stream);
+        self.bit_rate = stream_bit_rate(stream);
+        let time_base = stream.time_base();
+        if time_base.numerator() > 0 && time_base.denominator() > 0 {
+            self.time_base = Some(format!("{}/{}", time_base.numerator(), time_base.denominator()));
+        }
+        self.rotation = rotation(stream);
+
+        let rate = match stream.avg_frame_rate() {
+            r if r.numerator() > 0 && r.denominator() > 0 => r,
+            _ => stream.rate(),
+        };
+        if rate.numerator() > 0 && rate.denominator() > 0 {
+            self.fps = Some(f64::from(rate));
+            self.frame_rate = Some(format!("{}/{}", rate.numerator(), rate.denominator()));
+        }
+
+        // 没有解码器时只保留流的基本信息
+        let codec = ffmpeg::codec::context::Context::from_parameters(stream.parameters())?;
+        let Ok(v) = codec.decoder().video() else {
+            return Ok(());
+        };
+        self.width = v.width();
+        self.height = v.height();
+        if let Some(format) = v.format().descriptor() {
+            self.format = Some(format.name().to_string());
+        }
+        self.color_transfer = v.color_transfer_characteristic().name().map(String::from);
+
+        // 未知时视为方形像素
+        let sar = v.aspect_ratio();
+        let (sar_num, sar_den) = match (sar.numerator(), sar.denominator()) {
+            (n, d) if n > 0 && d > 0 => (n as u64, d as u64),
+            _ => (1, 1),
+        };
+        self.sar = Some(ratio(sar_num, sar_den));
+        if self.width > 0 && self.height > 0 {
+            self.dar = Some(ratio(self.width as u64 * sar_num, self.height as u64 * sar_den));
+        }
+        Ok(())
+    }
+}
+
+/// 编码档次名称
+fn profile_name(stream: &Stream) -> Option<String> {
+    let params = stream.parameters();
+    unsafe {
+        let par = params.as_ptr();
+        let name = ffmpeg::ffi::avcodec_profile_name((*par).codec_id, (*par).profile);
+        if name.is_null() {
+            return None;
+        }
+        Some(CStr::from_ptr(name).to_string_lossy().into_owned())
+    }
+}
+
+/// 编码级别，未知时为 `None`
+fn level(stream: &Stream) -> Option<i32> {
+    let level = unsafe { (*stream.parameters().as_ptr()).level };
+    (level > 0).then_some(level)
+}
+
+fn stream_bit_rate(stream: &Stream) -> Option<u64> {
+    let params = stream.parameters();
+    let bit_rate = unsafe { (*params.as_ptr()).bit_rate };
+    (bit_rate > 0).then_some(bit_rate as u64)
+}
+
+/// 从 display matrix 计算旋转角度，和 `av_display_rotation_get` 一致
+fn rotation(stream: &Stream) -> Option<f64> {
+    let side = stream.side_data().find(|x| x.kind() == side_data::Type::DisplayMatrix)?;
+    let data = side.data();
+    if data.len() < 36 {
+        return None;
+    }
+    let m: Vec<f64> = data[..36]
+        .chunks_exact(4)
+        .map(|x| i32::from_ne_bytes([x[0], x[1], x[2], x[3]]) as f64)
+        .collect();
+    let scale0 = m[0].hypot(m[3]);
+    let scale1 = m[1].hypot(m[4]);
+    if scale0 == 0.0 || scale1 == 0.0 {
+        return None;
+    }
+    let degrees = -(m[1] / scale1).atan2(m[0] / scale0).to_degrees();
+    Some(if degrees == 0.0 { 0.0 } else { degrees })
+}
+
+/// 化简后的比例字符串
+fn ratio(a: u64, b: u64) -> String {
+    fn gcd(a: u64, b: u64) -> u64 {
+        if b == 0 { a } else { gcd(b, a % b) }
+    }
+    let g = gcd(a, b).max(1);
+    format!("{}:{}", a / g, b / g)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ratio, Video};
+
+    #[test]
+    fn test_ratio() {
+        assert_eq!(ratio(1920, 1080), "16:9");
+        assert_eq!(ratio(1, 1), "1:1");
+        assert_eq!(ratio(1440 * 4, 1080 * 3), "16:9");
+    }
+
+    #[test]
+    fn test_old_json() {
+        // 旧版本保存的 part.json 没有新增的字段
+        let video: Video = serde_json::from_str(
+            r#"{"width":1920,"height":1080,"size":1,"duration":2.5,"format":"yuv420p","path":"/tmp/a.ts"}"#,
+        ).unwrap();
+        assert_eq!(video.codec, None);
+        assert!(video.audios.is_empty());
+    }
 }
@@ -0,0 +1,323 @@
+use std::{
+    cell::RefCell,
+    error::Error,
+    fmt, fs,
+    io::{BufRead, BufReader, Read},
+    path::PathBuf,
+    process::{Command, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, RecvTimeoutError},
+        Arc, Mutex, OnceLock,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Result};
+
+use crate::{
+    progress::{progress_handler, ProgressParser},
+    FfmpegCommand,
+};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+static TOKEN: OnceLock<CancelToken> = OnceLock::new();
+static TIMEOUT: Mutex<Option<Duration>> = Mutex::new(None);
+
+thread_local! {
+    static SCOPED: RefCell<Option<CancelToken>> = const { RefCell::new(None) };
+}
+
+/// 取消令牌
+///
+/// 取消后正在执行的子进程会被终止，后续步骤不再执行
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    /// 已取消时返回 `ProcessError::Cancelled`
+    ///
+    /// Examples
+    ///
+    /// ```
+    /// use bili_video::CancelToken;
+    ///
+    /// let token = CancelToken::new();
+    /// assert!(token.check().is_ok());
+    /// token.cancel();
+    /// assert!(token.check().is_err());
+    /// ```
+    pub fn check(&self) -> Result<(), ProcessError> {
+        if self.is_cancelled() {
+            Err(ProcessError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// 子进程执行错误
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProcessError {
+    /// 任务被取消
+    Cancelled,
+    /// 单步执行超时
+    Timeout { program: String, timeout: Duration },
+}
+
+impl fmt::Display for ProcessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProcessError::Cancelled => write!(f, "任务已取消"),
+            ProcessError::Timeout { program, timeout } => {
+                write!(f, "{} 执行超过 {:?}，已终止", program, timeout)
+            }
+        }
+    }
+}
+
+impl Error for ProcessError {}
+
+/// 获取进程内默认的取消令牌
+///
+/// 命令行收到 Ctrl-C 时取消该令牌；没有通过 `with_cancel_token`、`Spliter::set_cancel_token`
+/// 等指定令牌的任务都检查它，取消后不会恢复
+pub fn cancel_token() -> CancelToken {
+    TOKEN.get_or_init(CancelToken::new).clone()
+}
+
+/// 当前线程生效的取消令牌
+///
+/// 优先使用 `with_cancel_token` 设置的令牌，默认为 `cancel_token`
+pub fn current_cancel_token() -> CancelToken {
+    SCOPED.with(|x| x.borrow().clone()).unwrap_or_else(cancel_token)
+}
+
+/// 在当前线程中使用指定令牌执行 `f`，期间的 ffmpeg 和外部命令只检查该令牌
+///
+/// Examples
+///
+/// ```
+/// use bili_video::{cancel_token, current_cancel_token, with_cancel_token, CancelToken};
+///
+/// let token = CancelToken::new();
+/// token.cancel();
+/// with_cancel_token(token, || assert!(current_cancel_token().is_cancelled()));
+/// assert!(!cancel_token().is_cancelled());
+/// ```
+pub fn with_cancel_token<F, R>(token: CancelToken, f: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    let prev = SCOPED.with(|x| x.replace(Some(token)));
+    // 即使 f panic 也要恢复之前的令牌
+    struct Restore(Option<CancelToken>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            let prev = self.0.take();
+            SCOPED.with(|x| *x.borrow_mut() = prev);
+        }
+    }
+    let _restore = Restore(prev);
+    f()
+}
+
+/// 设置单个子进程的最长执行时间，`None` 表示不限制
+pub fn set_step_timeout(timeout: Option<Duration>) {
+    *TIMEOUT.lock().unwrap() = timeout;
+}
+
+/// 获取单个子进程的最长执行时间
+pub fn step_timeout() -> Option<Duration> {
+    *TIMEOUT.lock().unwrap()
+}
+
+/// 执行外部命令，输出并返回标准输出的每一行
+///
+/// 支持取消和超时，检查当前线程生效的取消令牌
+///
+/// Examples
+///
+/// ```ignore
+/// let lines = bili_video::spawn(["biliup", "--help"]).unwrap();
+/// ```
+pub fn spawn<I, S>(args: I) -> Result<Vec<String>>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let args = to_args(args);
+    let mut lines = Vec::new();
+    run(&args, &[], &[], &current_cancel_token(), false, |line| {
+        println!("{}", line);
+        lines.push(line);
+    })?;
+    Ok(lines)
+}
+
+/// 执行 ffmpeg 并回调进度
+///
+/// 取消或超时时会删除 `outputs` 中未完成的输出文件
+pub(crate) fn spawn_ffmpeg(cmd: &FfmpegCommand) -> Result<()> {
+    let (args, outputs) = (cmd.argv(), cmd.outputs());
+    let token = cmd.cancel_token().unwrap_or_else(current_cancel_token);
+    match progress_handler() {
+        Some(handler) => {
+            let mut parser = ProgressParser::new(cmd.total());
+            parser.set_offset(cmd.progress_offset());
+            let extra = ["-hide_banner", "-loglevel", "error", "-nostats", "-progress", "pipe:1"];
+            run(&args, &extra, &outputs, &token, false, |line| {
+                if let Some(p) = parser.feed(&line) {
+                    handler(&p);
+                }
+            })?;
+        }
+        None => {
+            run(&args, &[], &outputs, &token, false, |line| println!("{}", line))?;
+        }
+    }
+    Ok(())
+}
+
+/// 执行 ffmpeg 分析命令并返回标准错误输出，如 `loudnorm`、`silencedetect` 的结果
+///
+/// 分析结果在 info 日志级别输出，不能使用 `-loglevel error`
+pub(crate) fn analyze_ffmpeg(cmd: &FfmpegCommand) -> Result<String> {
+    let args = cmd.argv();
+    let token = cmd.cancel_token().unwrap_or_else(current_cancel_token);
+    match progress_handler() {
+        Some(handler) => {
+            let mut parser = ProgressParser::new(cmd.total());
+            parser.set_offset(cmd.progress_offset());
+            let extra = ["-hide_banner", "-nostats", "-progress", "pipe:1"];
+            run(&args, &extra, &[], &token, true, |line| {
+                if let Some(p) = parser.feed(&line) {
+                    handler(&p);
+                }
+            })
+        }
+        None => run(&args, &["-hide_banner", "-nostats"], &[], &token, true, |line| println!("{}", line)),
+    }
+}
+
+fn to_args<I, S>(args: I) -> Vec<String>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    args.into_iter().map(|x| x.as_ref().to_string()).collect()
+}
+
+/// 执行子进程，期间轮询取消令牌和超时时间
+///
+/// `capture_stderr` 为 true 时返回标准错误输出，否则返回空字符串
+fn run<F>(
+    args: &[String],
+    extra: &[&str],
+    outputs: &[PathBuf],
+    token: &CancelToken,
+    capture_stderr: bool,
+    mut on_line: F,
+) -> Result<String>
+where
+    F: FnMut(String),
+{
+    token.check()?;
+
+    let (program, rest) = args.split_first().ok_or(anyhow!("empty command"))?;
+    let mut command = Command::new(program);
+    command.args(extra).args(rest).stdout(Stdio::piped());
+    if capture_stderr {
+        command.stderr(Stdio::piped());
+    }
+    let mut child = command.spawn()?;
+
+    // 标准错误输出可能很长，需要同时读取，避免子进程阻塞
+    let stderr = child.stderr.take().map(|mut stderr| {
+        thread::spawn(move || {
+            let mut buf = String::new();
+            let _ = stderr.read_to_string(&mut buf);
+            buf
+        })
+    });
+
+    // 单独线程读取输出，避免阻塞轮询
+    let (tx, rx) = mpsc::channel();
+    if let Some(stdout) = child.stdout.take() {
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                if tx.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+
+    let deadline = step_timeout().map(|t| (t, Instant::now() + t));
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+
+        let interrupted = if token.is_cancelled() {
+            Some(ProcessError::Cancelled)
+        } else {
+            match deadline {
+                Some((timeout, at)) if Instant::now() > at => Some(ProcessError::Timeout {
+                    program: program.clone(),
+                    timeout,
+                }),
+                _ => None,
+            }
+        };
+        if let Some(err) = interrupted {
+            let _ = child.kill();
+            let _ = child.wait();
+            remove_outputs(outputs);
+            return Err(err.into());
+        }
+
+        match rx.recv_timeout(POLL_INTERVAL) {
+            Ok(line) => on_line(line),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => thread::sleep(POLL_INTERVAL),
+        }
+    };
+    for line in rx {
+        on_line(line);
+    }
+
+    // Ctrl-C 时子进程会先于我们退出
+    if token.is_cancelled() {
+        remove_outputs(outputs);
+        return Err(ProcessError::Cancelled.into());
+    }
+    let stderr = stderr.and_then(|x| x.join().ok()).unwrap_or_default();
+    if !status.success() {
+        return Err(anyhow!("{} failed: {}", program, status));
+    }
+    Ok(stderr)
+}
+
+/// 删除未写完的输出文件
+fn remove_outputs(outputs: &[PathBuf]) {
+    for path in outputs {
+        if path.is_file() {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
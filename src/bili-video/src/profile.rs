@@ -0,0 +1,653 @@
+use std::{fmt, path::Path};
+
+use anyhow::Result;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    executor, loudnorm::loudnorm_filter, twopass::encode_to_size, FfmpegCommand, Loudnorm, Output,
+    Video,
+};
+
+/// 转码配置
+///
+/// 配置文件中通过 `ProfilePatch` 覆盖内置配置或其他配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct TranscodeProfile {
+    pub name: String,
+    /// 视频编码器，如 `libx264`、`libx265`
+    pub video_codec: String,
+    pub preset: String,
+    pub crf: u8,
+    pub maxrate: Option<String>,
+    pub bufsize: Option<String>,
+    /// 输出帧率，`None` 时保持原帧率
+    pub fps: Option<f64>,
+    /// 输出画面宽度，原画面按比例缩放后居中填充
+    pub width: u32,
+    pub height: u32,
+    pub pix_fmt: Option<String>,
+    /// 视频标签，HEVC 使用 `hvc1` 以便播放器识别
+    pub tag: Option<String>,
+    pub audio_codec: String,
+    pub audio_bitrate: Option<String>,
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u16>,
+    /// 目标文件大小（MB），设置后使用两遍编码控制码率
+    pub target_size: Option<f64>,
+    /// 响度标准化参数，设置后音频总是重新编码
+    pub loudnorm: Option<Loudnorm>,
+}
+
+impl Default for TranscodeProfile {
+    fn default() -> Self {
+        Self {
+            name: "1080p".to_string(),
+            video_codec: "libx264".to_string(),
+            preset: "veryfast".to_string(),
+            crf: 23,
+            maxrate: Some("17185k".to_string()),
+            bufsize: Some("34370k".to_string()),
+            fps: Some(25.0),
+            width: 1920,
+            height: 1080,
+            pix_fmt: None,
+            tag: None,
+            audio_codec: "aac".to_string(),
+            audio_bitrate: Some("319k".to_string()),
+            sample_rate: Some(48000),
+            channels: Some(2),
+            target_size: None,
+            loudnorm: None,
+        }
+    }
+}
+
+/// 转码配置的覆盖项
+///
+/// 在 TOML 中写作 `[[profiles]]`，只需要填写和基础配置不同的字段。
+/// 基础配置默认是下一层的同名配置（`bilibili.toml` 或内置配置），也可以用 `base` 指定，
+/// 都没有时使用 `1080p`
+///
+/// ```toml
+/// [[profiles]]
+/// name = "720p"
+/// crf = 20
+///
+/// [[profiles]]
+/// name = "1080p-hq"
+/// crf = 20
+/// preset = "slow"
+/// loudnorm = { i = -16.0, tp = -1.5, lra = 11.0 }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ProfilePatch {
+    pub name: String,
+    /// 基础配置的名称，默认为同名配置
+    pub base: Option<String>,
+    pub video_codec: Option<String>,
+    pub preset: Option<String>,
+    pub crf: Option<u8>,
+    pub maxrate: Option<String>,
+    pub bufsize: Option<String>,
+    pub fps: Option<f64>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub pix_fmt: Option<String>,
+    pub tag: Option<String>,
+    pub audio_codec: Option<String>,
+    pub audio_bitrate: Option<String>,
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u16>,
+    pub target_size: Option<f64>,
+    pub loudnorm: Option<Loudnorm>,
+}
+
+impl ProfilePatch {
+    /// 基础配置的名称
+    pub fn base_name(&self) -> &str {
+        self.base.as_deref().unwrap_or(&self.name)
+    }
+
+    /// 覆盖到基础配置上，名称使用覆盖项的名称
+    pub fn apply_to(&self, base: TranscodeProfile) -> TranscodeProfile {
+        let p = self.clone();
+        TranscodeProfile {
+            name: p.name,
+            video_codec: p.video_codec.unwrap_or(base.video_codec),
+            preset: p.preset.unwrap_or(base.preset),
+            crf: p.crf.unwrap_or(base.crf),
+            maxrate: p.maxrate.or(base.maxrate),
+            bufsize: p.bufsize.or(base.bufsize),
+            fps: p.fps.or(base.fps),
+            width: p.width.unwrap_or(base.width),
+            height: p.height.unwrap_or(base.height),
+            pix_fmt: p.pix_fmt.or(base.pix_fmt),
+            tag: p.tag.or(base.tag),
+            audio_codec: p.audio_codec.unwrap_or(base.audio_codec),
+            audio_bitrate: p.audio_bitrate.or(base.audio_bitrate),
+            sample_rate: p.sample_rate.or(base.sample_rate),
+            channels: p.channels.or(base.channels),
+            target_size: p.target_size.or(base.target_size),
+            loudnorm: p.loudnorm.or(base.loudnorm),
+        }
+    }
+}
+
+/// 按名称解析多层覆盖后的转码配置
+///
+/// `layers` 从低到高排列，如 `bilibili.toml`、媒体配置；最底层是内置配置。
+/// 没有任何一层定义该名称时返回 `None`，`base` 循环引用时以 `1080p` 为基础
+///
+/// Examples
+///
+/// ```
+/// use bili_video::{resolve_profile, ProfilePatch};
+///
+/// let patch = |name: &str, base: Option<&str>, crf: u8| ProfilePatch {
+///     name: name.into(),
+///     base: base.map(String::from),
+///     crf: Some(crf),
+///     ..Default::default()
+/// };
+/// let settings = vec![patch("720p", None, 21)];
+/// let media = vec![patch("720p", None, 20), patch("vertical-hq", Some("vertical"), 18)];
+///
+/// // 只覆盖填写的字段，其余来自下一层和内置的 720p
+/// let profile = resolve_profile("720p", &[&settings, &media]).unwrap();
+/// assert_eq!((profile.width, profile.height, profile.crf), (1280, 720, 20));
+///
+/// let profile = resolve_profile("vertical-hq", &[&settings, &media]).unwrap();
+/// assert_eq!((profile.width, profile.height, profile.crf), (1080, 1920, 18));
+///
+/// assert_eq!(resolve_profile("720p", &[&settings]).unwrap().crf, 21);
+/// assert!(resolve_profile("8k", &[&settings, &media]).is_none());
+/// ```
+pub fn resolve_profile(name: &str, layers: &[&[ProfilePatch]]) -> Option<TranscodeProfile> {
+    resolve_with(name, layers, &mut Vec::new())
+}
+
+fn resolve_with(name: &str, layers: &[&[ProfilePatch]], stack: &mut Vec<String>) -> Option<TranscodeProfile> {
+    let Some((top, rest)) = layers.split_last() else {
+        return TranscodeProfile::builtin(name);
+    };
+    let Some(patch) = top.iter().find(|x| x.name == name) else {
+        return resolve_with(name, rest, stack);
+    };
+    let base_name = patch.base_name();
+    let base = if base_name == name {
+        resolve_with(name, rest, stack)
+    } else if stack.iter().any(|x| x == base_name) {
+        None
+    } else {
+        // 不同名称的基础配置从最高层开始查找
+        stack.push(name.to_string());
+        let base = resolve_with(base_name, layers, stack);
+        stack.pop();
+        base
+    };
+    Some(patch.apply_to(base.unwrap_or_default()))
+}
+
+impl TranscodeProfile {
+    /// 内置的转码配置：`720p`、`1080p`、`4k-hevc`、`60fps`、`vertical`
+    pub fn builtins() -> Vec<Self> {
+        let default = Self::default();
+        vec![
+            Self {
+                name: "720p".to_string(),
+                maxrate: Some("8000k".to_string()),
+                bufsize: Some("16000k".to_string()),
+                width: 1280,
+                height: 720,
+                audio_bitrate: Some("192k".to_string()),
+                ..default.clone()
+            },
+            default.clone(),
+            Self {
+                name: "4k-hevc".to_string(),
+                video_codec: "libx265".to_string(),
+                preset: "fast".to_string(),
+                crf: 26,
+                maxrate: Some("40000k".to_string()),
+                bufsize: Some("80000k".to_string()),
+                fps: None,
+                width: 3840,
+                height: 2160,
+                pix_fmt: Some("yuv420p10le".to_string()),
+                tag: Some("hvc1".to_string()),
+                ..default.clone()
+            },
+            Self {
+                name: "60fps".to_string(),
+                maxrate: Some("25000k".to_string()),
+                bufsize: Some("50000k".to_string()),
+                fps: Some(60.0),
+                ..default.clone()
+            },
+            Self {
+                name: "vertical".to_string(),
+                fps: Some(30.0),
+                width: 1080,
+                height: 1920,
+                ..default
+            },
+        ]
+    }
+
+    /// 按名称获取内置的转码配置
+    ///
+    /// Examples
+    ///
+    /// ```
+    /// use bili_video::TranscodeProfile;
+    ///
+    /// let profile = TranscodeProfile::builtin("720p").unwrap();
+    /// assert_eq!((profile.width, profile.height), (1280, 720));
+    /// assert_eq!(TranscodeProfile::builtin("1080p"), Some(TranscodeProfile::default()));
+    /// assert!(TranscodeProfile::builtin("8k").is_none());
+    /// ```
+    pub fn builtin(name: &str) -> Option<Self> {
+        Self::builtins().into_iter().find(|x| x.name == name)
+    }
+
+    /// 缩放并居中填充到目标分辨率的滤镜
+    pub fn scale_filter(&self) -> String {
+        format!(
+            "scale={w}:{h}:force_original_aspect_ratio=decrease,pad={w}:{h}:(ow-iw)/2:(oh-ih)/2",
+            w = self.width,
+            h = self.height,
+        )
+    }
+
+    /// 把转码参数添加到输出
+    ///
+    /// Examples
+    ///
+    /// ```
+    /// use bili_video::{FfmpegCommand, TranscodeProfile};
+    ///
+    /// let mut cmd = FfmpegCommand::new();
+    /// cmd.input("/tmp/in.mp4");
+    /// TranscodeProfile::builtin("4k-hevc").unwrap().apply(cmd.output("/tmp/out.mp4"));
+    /// let args = cmd.args();
+    /// assert!(args.windows(2).any(|x| x == ["-c:v", "libx265"]));
+    /// assert!(args.windows(2).any(|x| x == ["-tag:v", "hvc1"]));
+    /// assert!(!args.contains(&"-r".to_string()));
+    /// ```
+    pub fn apply<'a>(&self, output: &'a mut Output) -> &'a mut Output {
+        self.apply_video(output);
+        self.apply_audio(output)
+    }
+
+    /// 只添加视频转码参数
+    pub fn apply_video<'a>(&self, output: &'a mut Output) -> &'a mut Output {
+        output
+            .video_codec(&self.video_codec)
+            .option("-preset", &self.preset);
+        if let Some(maxrate) = &self.maxrate {
+            output.option("-maxrate", maxrate);
+        }
+        if let Some(bufsize) = &self.bufsize {
+            output.option("-bufsize", bufsize);
+        }
+        output.option("-crf", &self.crf.to_string());
+        self.apply_picture(output)
+    }
+
+    /// 帧率、分辨率、像素格式等画面参数
+    pub(crate) fn apply_picture<'a>(&self, output: &'a mut Output) -> &'a mut Output {
+        if let Some(fps) = self.fps {
+            output.option("-r", &fps.to_string());
+        }
+        output.video_filter(&self.scale_filter());
+        if let Some(pix_fmt) = &self.pix_fmt {
+            output.option("-pix_fmt", pix_fmt);
+        }
+        if let Some(tag) = &self.tag {
+            output.option("-tag:v", tag);
+        }
+        output
+    }
+
+    /// 只添加音频转码参数
+    pub fn apply_audio<'a>(&self, output: &'a mut Output) -> &'a mut Output {
+        output.audio_codec(&self.audio_codec);
+        if let Some(bitrate) = &self.audio_bitrate {
+            output.option("-b:a", bitrate);
+        }
+        if let Some(rate) = self.sample_rate {
+            output.option("-ar", &rate.to_string());
+        }
+        if let Some(channels) = self.channels {
+            output.option("-ac", &channels.to_string());
+        }
+        output
+    }
+
+    /// 对比源视频和转码配置，符合要求的流直接复制
+    ///
+    /// Examples
+    ///
+    /// ```
+    /// use bili_video::{AudioStream, StreamAction, TranscodeProfile, Video};
+    ///
+    /// let mut video = Video {
+    ///     width: 1920,
+    ///     height: 1080,
+    ///     codec: Some("h264".into()),
+    ///     format: Some("yuv420p".into()),
+    ///     fps: Some(25.0),
+    ///     audios: vec![AudioStream {
+    ///         codec: "aac".into(),
+    ///         channels: 2,
+    ///         sample_rate: 48000,
+    ///         ..Default::default()
+    ///     }],
+    ///     ..Default::default()
+    /// };
+    /// let profile = TranscodeProfile::default();
+    /// let plan = profile.plan(&video);
+    /// assert!(plan.is_copy());
+    ///
+    /// // 只有音频不符合时只转码音频
+    /// video.audios[0].codec = "ac3".into();
+    /// let plan = profile.plan(&video);
+    /// assert_eq!(plan.video, StreamAction::Copy);
+    /// assert_eq!(plan.audio, StreamAction::Encode);
+    /// assert_eq!(plan.reasons, vec!["音频编码 ac3 不是 aac"]);
+    ///
+    /// // 设置了目标大小时视频总是重新编码
+    /// let profile = TranscodeProfile { target_size: Some(800.0), ..Default::default() };
+    /// let plan = profile.plan(&video);
+    /// assert_eq!(plan.video, StreamAction::Encode);
+    /// assert_eq!(plan.reasons[0], "目标大小 800MB");
+    /// ```
+    pub fn plan(&self, video: &Video) -> TranscodePlan {
+        let mut reasons = Vec::new();
+
+        let codec = encoder_codec(&self.video_codec);
+        match video.codec.as_deref() {
+            Some(c) if c == codec => {}
+            c => reasons.push(format!("视频编码 {} 不是 {}", c.unwrap_or("未知"), codec)),
+        }
+        if (video.width, video.height) != (self.width, self.height) {
+            reasons.push(format!(
+                "分辨率 {}x{} 不是 {}x{}",
+                video.width, video.height, self.width, self.height
+            ));
+        }
+        if let Some(fps) = self.fps {
+            match video.fps {
+                Some(f) if (f - fps).abs() < 0.01 => {}
+                f => reasons.push(format!("帧率 {} 不是 {}", f.map_or("未知".to_string(), |x| format!("{:.3}", x)), fps)),
+            }
+        }
+        let pix_fmt = self.pix_fmt.as_deref().unwrap_or("yuv420p");
+        match video.format.as_deref() {
+            Some(f) if f == pix_fmt => {}
+            f => reasons.push(format!("像素格式 {} 不是 {}", f.unwrap_or("未知"), pix_fmt)),
+        }
+        if let (Some(rate), Some(max)) = (video.bit_rate, self.maxrate.as_deref().and_then(parse_bitrate)) {
+            if rate > max {
+                reasons.push(format!("视频码率 {}k 超过 {}k", rate / 1000, max / 1000));
+            }
+        }
+        if let Some(rotation) = video.rotation.filter(|x| *x != 0.0) {
+            reasons.push(format!("画面旋转 {}°", rotation));
+        }
+        if let Some(sar) = video.sar.as_deref().filter(|x| *x != "1:1") {
+            reasons.push(format!("像素宽高比 {} 不是 1:1", sar));
+        }
+        // 目标大小需要两遍编码控制码率，不能直接复制
+        if let Some(size) = self.target_size {
+            reasons.push(format!("目标大小 {}MB", size));
+        }
+        let video_action = if reasons.is_empty() { StreamAction::Copy } else { StreamAction::Encode };
+
+        let video_reasons = reasons.len();
+        if let Some(audio) = video.audios.first() {
+            let codec = encoder_codec(&self.audio_codec);
+            if audio.codec != codec {
+                reasons.push(format!("音频编码 {} 不是 {}", audio.codec, codec));
+            }
+            if let Some(rate) = self.sample_rate.filter(|x| *x != audio.sample_rate) {
+                reasons.push(format!("采样率 {} 不是 {}", audio.sample_rate, rate));
+            }
+            if let Some(channels) = self.channels.filter(|x| *x != audio.channels) {
+                reasons.push(format!("声道数 {} 不是 {}", audio.channels, channels));
+            }
+            if let (Some(rate), Some(max)) = (audio.bit_rate, self.audio_bitrate.as_deref().and_then(parse_bitrate)) {
+                if rate > max {
+                    reasons.push(format!("音频码率 {}k 超过 {}k", rate / 1000, max / 1000));
+                }
+            }
+            if self.loudnorm.is_some() {
+                reasons.push("响度标准化".to_string());
+            }
+        }
+        let audio_action = if reasons.len() > video_reasons { StreamAction::Encode } else { StreamAction::Copy };
+
+        TranscodePlan { video: video_action, audio: audio_action, reasons }
+    }
+}
+
+/// 流的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamAction {
+    /// 直接复制
+    Copy,
+    /// 按转码配置重新编码
+    Encode,
+}
+
+/// 转码计划，记录每种流的处理方式和重新编码的原因
+#[derive(Debug, Clone, PartialEq)]
+pub struct TranscodePlan {
+    pub video: StreamAction,
+    pub audio: StreamAction,
+    pub reasons: Vec<String>,
+}
+
+impl TranscodePlan {
+    /// 全部重新编码
+    pub fn encode_all() -> Self {
+        Self { video: StreamAction::Encode, audio: StreamAction::Encode, reasons: Vec::new() }
+    }
+
+    /// 所有流都直接复制
+    pub fn is_copy(&self) -> bool {
+        self.video == StreamAction::Copy && self.audio == StreamAction::Copy
+    }
+}
+
+impl fmt::Display for TranscodePlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let action = |x: StreamAction| match x {
+            StreamAction::Copy => "复制",
+            StreamAction::Encode => "重新编码",
+        };
+        write!(f, "视频{}，音频{}", action(self.video), action(self.audio))?;
+        if !self.reasons.is_empty() {
+            write!(f, "（{}）", self.reasons.join("；"))?;
+        }
+        Ok(())
+    }
+}
+
+/// 编码器输出的编码名称
+fn encoder_codec(encoder: &str) -> &str {
+    match encoder {
+        "libx264" | "h264_nvenc" | "h264_qsv" | "h264_videotoolbox" => "h264",
+        "libx265" | "hevc_nvenc" | "hevc_qsv" | "hevc_videotoolbox" => "hevc",
+        "libsvtav1" | "libaom-av1" => "av1",
+        "libvpx-vp9" => "vp9",
+        "libfdk_aac" => "aac",
+        "libmp3lame" => "mp3",
+        "libopus" => "opus",
+        x => x,
+    }
+}
+
+/// 解析 `17185k`、`8M` 形式的码率
+pub(crate) fn parse_bitrate(s: &str) -> Option<u64> {
+    let s = s.trim();
+    let (num, unit) = match s.chars().last()? {
+        'k' | 'K' => (&s[..s.len() - 1], 1_000.0),
+        'm' | 'M' => (&s[..s.len() - 1], 1_000_000.0),
+        _ => (s, 1.0),
+    };
+    num.parse::<f64>().ok().map(|x| (x * unit) as u64)
+}
+
+/// 按转码配置转码
+///
+/// Examples
+///
+/// ```ignore
+/// use bili_video::{transcode, TranscodeProfile};
+///
+/// let profile = TranscodeProfile::builtin("720p").unwrap();
+/// transcode("/tmp/test.mp4", "/tmp/test.720p.mp4", &profile).unwrap();
+/// ```
+pub fn transcode<F, T>(from: F, to: T, profile: &TranscodeProfile) -> Result<()>
+where
+    F: AsRef<Path>,
+    T: AsRef<Path>,
+{
+    transcode_with_plan(from, to, profile, &TranscodePlan::encode_all())
+}
+
+/// 按转码计划转码，复制的流不重新编码
+///
+/// Examples
+///
+/// ```ignore
+/// use bili_video::{executor, transcode_with_plan, TranscodeProfile};
+///
+/// let profile = TranscodeProfile::default();
+/// let plan = profile.plan(&executor().probe("/tmp/test.mp4".as_ref()).unwrap());
+/// println!("{}", plan);
+/// transcode_with_plan("/tmp/test.mp4", "/tmp/test.1080p.mp4", &profile, &plan).unwrap();
+/// ```
+pub fn transcode_with_plan<F, T>(from: F, to: T, profile: &TranscodeProfile, plan: &TranscodePlan) -> Result<()>
+where
+    F: AsRef<Path>,
+    T: AsRef<Path>,
+{
+    let filter = match plan.audio {
+        StreamAction::Encode => loudnorm_filter(from.as_ref(), profile)?,
+        StreamAction::Copy => None,
+    };
+    // 设置了目标大小时使用两遍编码
+    if let (StreamAction::Encode, Some(size)) = (plan.video, profile.target_size) {
+        return encode_to_size(from.as_ref(), to.as_ref(), profile, None, size, plan.audio, filter.as_deref());
+    }
+
+    let total = executor().probe(from.as_ref()).ok().map(|v| v.duration);
+    let mut cmd = FfmpegCommand::new();
+    cmd.input(&from);
+    let output = cmd.output(&to);
+    match plan.video {
+        StreamAction::Copy => output.video_codec("copy"),
+        StreamAction::Encode => profile.apply_video(output),
+    };
+    match plan.audio {
+        StreamAction::Copy => output.audio_codec("copy"),
+        StreamAction::Encode => profile.apply_audio(output),
+    };
+    if let Some(filter) = &filter {
+        output.audio_filter(filter);
+    }
+    cmd.set_total(total).run()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, sync::Arc};
+
+    use crate::{with_executor, Loudnorm, RecordingExecutor, StreamAction, TranscodePlan, TranscodeProfile};
+
+    use super::{parse_bitrate, transcode_with_plan};
+
+    #[test]
+    fn test_parse_bitrate() {
+        assert_eq!(parse_bitrate("17185k"), Some(17_185_000));
+        assert_eq!(parse_bitrate("8M"), Some(8_000_000));
+        assert_eq!(parse_bitrate("128000"), Some(128_000));
+        assert_eq!(parse_bitrate("fast"), None);
+    }
+
+    #[test]
+    fn test_transcode_audio_only() {
+        let from = env::temp_dir().join("bili-video-profile-from.mp4");
+        let to = env::temp_dir().join("bili-video-profile-to.mp4");
+        let plan = TranscodePlan {
+            video: StreamAction::Copy,
+            audio: StreamAction::Encode,
+            reasons: Vec::new(),
+        };
+
+        let rec = Arc::new(RecordingExecutor::new());
+        with_executor(rec.clone(), || {
+            transcode_with_plan(&from, &to, &TranscodeProfile::default(), &plan).unwrap();
+        });
+
+        let from = from.to_str().unwrap();
+        let to_str = to.to_str().unwrap();
+        assert_eq!(rec.calls(), vec![vec![
+            "ffmpeg", "-i", from, "-c:v", "copy",
+            "-c:a", "aac", "-b:a", "319k", "-ar", "48000", "-ac", "2", to_str,
+        ]]);
+        std::fs::remove_file(to).unwrap();
+    }
+
+    #[test]
+    fn test_transcode_loudnorm() {
+        let from = env::temp_dir().join("bili-video-loudnorm-from.mp4");
+        let to = env::temp_dir().join("bili-video-loudnorm-to.mp4");
+        let profile = TranscodeProfile { loudnorm: Some(Loudnorm::default()), ..Default::default() };
+        let plan = TranscodePlan {
+            video: StreamAction::Copy,
+            audio: StreamAction::Encode,
+            reasons: Vec::new(),
+        };
+
+        let rec = Arc::new(RecordingExecutor::new());
+        rec.set_stderr(r#"[Parsed_loudnorm_0 @ 0x600000]
+{
+    "input_i" : "-27.61",
+    "input_tp" : "-4.47",
+    "input_lra" : "18.06",
+    "input_thresh" : "-39.20",
+    "target_offset" : "0.58"
+}"#);
+        with_executor(rec.clone(), || {
+            transcode_with_plan(&from, &to, &profile, &plan).unwrap();
+        });
+
+        let calls = rec.calls();
+        assert_eq!(calls.len(), 2);
+        // 第一遍只测量音频
+        assert_eq!(calls[0][1..], [
+            "-i", from.to_str().unwrap(), "-map", "0:a:0",
+            "-af", "loudnorm=I=-16:TP=-1.5:LRA=11:print_format=json", "-f", "null", "/dev/null",
+        ]);
+        // 第二遍使用测量结果
+        let af = calls[1].iter().position(|x| x == "-af").unwrap();
+        assert!(calls[1][af + 1].contains("measured_I=-27.61"));
+        assert!(calls[1][af + 1].contains("linear=true"));
+        std::fs::remove_file(&to).unwrap();
+
+        // 静音时不使用响度滤镜，不中断转码
+        let rec = Arc::new(RecordingExecutor::new());
+        rec.set_stderr("[Parsed_loudnorm_0 @ 0x600000]\n{\n    \"input_i\" : \"-inf\"\n}");
+        with_executor(rec.clone(), || {
+            transcode_with_plan(&from, &to, &profile, &plan).unwrap();
+        });
+        assert!(!rec.calls()[1].contains(&"-af".to_string()));
+        std::fs::remove_file(to).unwrap();
+    }
+}
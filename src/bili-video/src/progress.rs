@@ -0,0 +1,183 @@
+use std::{
+    sync::{mpsc, Arc, Mutex},
+    time::Duration,
+};
+
+/// ffmpeg 进度回调
+pub type ProgressHandler = Arc<dyn Fn(&Progress) + Send + Sync>;
+
+static HANDLER: Mutex<Option<ProgressHandler>> = Mutex::new(None);
+
+/// ffmpeg `-progress` 输出的进度事件
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Progress {
+    /// 已处理帧数
+    pub frame: u64,
+    /// 当前处理帧率
+    pub fps: f64,
+    /// 已输出的媒体时长（秒）
+    pub out_time: f64,
+    /// 处理速度，相对于实时播放的倍数
+    pub speed: Option<f64>,
+    /// 媒体总时长（秒）
+    pub total: Option<f64>,
+    /// 是否处理完成
+    pub done: bool,
+}
+
+impl Progress {
+    /// 完成百分比
+    ///
+    /// Examples
+    ///
+    /// ```
+    /// use bili_video::Progress;
+    ///
+    /// let p = Progress { out_time: 30.0, total: Some(120.0), ..Default::default() };
+    /// assert_eq!(p.percent(), Some(25.0));
+    /// ```
+    pub fn percent(&self) -> Option<f64> {
+        match self.total {
+            Some(total) if total > 0.0 => Some((self.out_time / total * 100.0).clamp(0.0, 100.0)),
+            _ => None,
+        }
+    }
+
+    /// 预计剩余时间
+    ///
+    /// Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use bili_video::Progress;
+    ///
+    /// let p = Progress { out_time: 30.0, total: Some(120.0), speed: Some(2.0), ..Default::default() };
+    /// assert_eq!(p.eta(), Some(Duration::from_secs(45)));
+    /// ```
+    pub fn eta(&self) -> Option<Duration> {
+        let total = self.total?;
+        let speed = self.speed?;
+        if speed <= 0.0 {
+            return None;
+        }
+        let remaining = (total - self.out_time).max(0.0) / speed;
+        Some(Duration::from_secs_f64(remaining))
+    }
+}
+
+/// 解析 `-progress` 输出的键值对，每遇到一个 `progress=` 生成一个进度事件
+#[derive(Debug, Default)]
+pub struct ProgressParser {
+    current: Progress,
+    offset: f64,
+}
+
+impl ProgressParser {
+    pub fn new(total: Option<f64>) -> Self {
+        Self { current: Progress { total, ..Default::default() }, offset: 0.0 }
+    }
+
+    /// 设置输出时间的起点
+    ///
+    /// 使用 `-copyts` 时输出时间从 seek 的位置开始，需要减去该偏移
+    ///
+    /// Examples
+    ///
+    /// ```
+    /// use bili_video::ProgressParser;
+    ///
+    /// let mut parser = ProgressParser::new(Some(100.0));
+    /// parser.set_offset(600.0);
+    /// parser.feed("out_time_us=610000000");
+    /// let p = parser.feed("progress=continue").unwrap();
+    /// assert_eq!(p.out_time, 10.0);
+    /// assert_eq!(p.percent(), Some(10.0));
+    /// ```
+    pub fn set_offset(&mut self, offset: f64) -> &mut Self {
+        self.offset = offset;
+        self
+    }
+
+    /// 解析一行输出
+    ///
+    /// Examples
+    ///
+    /// ```
+    /// use bili_video::ProgressParser;
+    ///
+    /// let mut parser = ProgressParser::new(Some(100.0));
+    /// assert!(parser.feed("frame=250").is_none());
+    /// assert!(parser.feed("out_time_us=10000000").is_none());
+    /// assert!(parser.feed("speed=2.5x").is_none());
+    /// let p = parser.feed("progress=continue").unwrap();
+    /// assert_eq!(p.frame, 250);
+    /// assert_eq!(p.out_time, 10.0);
+    /// assert_eq!(p.speed, Some(2.5));
+    /// assert_eq!(p.percent(), Some(10.0));
+    /// assert!(!p.done);
+    /// ```
+    pub fn feed(&mut self, line: &str) -> Option<Progress> {
+        let (key, value) = line.trim().split_once('=')?;
+        let value = value.trim();
+        match key {
+            "frame" => self.current.frame = value.parse().unwrap_or(self.current.frame),
+            "fps" => self.current.fps = value.parse().unwrap_or(self.current.fps),
+            // out_time_ms 虽然名字是毫秒，但 ffmpeg 实际输出的也是微秒
+            "out_time_us" | "out_time_ms" => {
+                if let Ok(us) = value.parse::<i64>() {
+                    self.current.out_time = (us as f64 / 1_000_000.0 - self.offset).max(0.0);
+                }
+            }
+            "speed" => self.current.speed = value.trim_end_matches('x').trim().parse().ok(),
+            "progress" => {
+                self.current.done = value == "end";
+                if self.current.done {
+                    if let Some(total) = self.current.total {
+                        self.current.out_time = total;
+                    }
+                }
+                return Some(self.current.clone());
+            }
+            _ => {}
+        }
+        None
+    }
+}
+
+/// 设置 ffmpeg 进度回调
+///
+/// 设置后所有 ffmpeg 调用都会解析 `-progress` 输出并回调
+///
+/// Examples
+///
+/// ```
+/// bili_video::set_progress_handler(|p| println!("{:?}", p.percent()));
+/// bili_video::clear_progress_handler();
+/// ```
+pub fn set_progress_handler<F>(f: F)
+where
+    F: Fn(&Progress) + Send + Sync + 'static,
+{
+    *HANDLER.lock().unwrap() = Some(Arc::new(f));
+}
+
+/// 清除 ffmpeg 进度回调
+pub fn clear_progress_handler() {
+    *HANDLER.lock().unwrap() = None;
+}
+
+/// 通过通道接收 ffmpeg 进度
+///
+/// 会替换已设置的进度回调
+pub fn progress_channel() -> mpsc::Receiver<Progress> {
+    let (tx, rx) = mpsc::channel();
+    let tx = Mutex::new(tx);
+    set_progress_handler(move |p| {
+        let _ = tx.lock().unwrap().send(p.clone());
+    });
+    rx
+}
+
+pub(crate) fn progress_handler() -> Option<ProgressHandler> {
+    HANDLER.lock().unwrap().clone()
+}
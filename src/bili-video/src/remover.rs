@@ -1,94 +1,393 @@
 use std::{fs, path::{Path, PathBuf}};
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 
-use crate::{concat, cut, cut_quick, to_ts, Video};
+use crate::{
+    check_duration, clamp_segments, concat, current_cancel_token, executor, normalize_segments, plan_cuts, with_cancel_token,
+    CancelToken, Cut, CutMode, Segment, SegmentError,
+};
 
 #[derive(Debug)]
 pub struct Remover {
     path: PathBuf,
-    segments: Vec<(u64, u64)>,
-    with_quick: bool,
+    segments: Vec<Segment>,
+    mode: CutMode,
+    tolerance: Option<f64>,
+    keep_only: bool,
+    token: Option<CancelToken>,
 }
 
 impl Remover {
-    pub fn new<P>(path: P, segments: Vec<(u64, u64)>) -> Self
+    pub fn new<P>(path: P, segments: Vec<Segment>) -> Self
         where P: AsRef<Path>
     {
-        Self { path: path.as_ref().to_path_buf(), segments, with_quick: false }
+        Self {
+            path: path.as_ref().to_path_buf(),
+            segments,
+            mode: CutMode::Accurate,
+            tolerance: None,
+            keep_only: false,
+            token: None,
+        }
+    }
+
+    /// 只保留指定片段，按给定的顺序合并，而不是删除它们
+    pub fn set_keep_only(&mut self, keep_only: bool) -> &mut Self {
+        self.keep_only = keep_only;
+        self
     }
 
     pub fn with_quick(&mut self, f: bool) -> &mut Self {
-        self.with_quick = f;
+        self.mode = if f { CutMode::Quick } else { CutMode::Accurate };
+        self
+    }
+
+    pub fn set_mode(&mut self, mode: CutMode) -> &mut Self {
+        self.mode = mode;
+        self
+    }
+
+    /// 快速模式下允许的关键帧偏差（秒），超过时该片段重新编码
+    pub fn set_tolerance(&mut self, tolerance: Option<f64>) -> &mut Self {
+        self.tolerance = tolerance;
         self
     }
 
-    /// 删除指定片段留下其余片段
+    /// 超出视频时长、截取时会被丢弃的片段
+    pub fn out_of_range(&self) -> Result<Vec<SegmentError>> {
+        let duration = executor().probe(&self.path)?.duration;
+        Ok(check_duration(&self.segments, duration))
+    }
+
+    /// 只检查指定的取消令牌，默认为当前线程生效的令牌
+    pub fn set_cancel_token(&mut self, token: CancelToken) -> &mut Self {
+        self.token = Some(token);
+        self
+    }
+
+    /// 保留片段的截取计划，快速模式下包含对齐关键帧后的实际边界
+    pub fn plan(&self) -> Result<Vec<Cut>> {
+        let e = executor();
+        let total_duration = e.probe(&self.path)?.duration;
+        let leave_parts = if self.keep_only {
+            Self::keep_segments(total_duration, self.segments.clone())?
+        } else {
+            Self::remove_segments(total_duration, self.segments.clone())?
+        };
+        if leave_parts.is_empty() {
+            return Err(anyhow!("{:?} 没有需要保留的片段", self.path));
+        }
+        let keyframes = match self.mode {
+            CutMode::Quick | CutMode::Smart => e.keyframes(&self.path)?,
+            CutMode::Accurate => Vec::new(),
+        };
+        Ok(plan_cuts(&leave_parts, self.mode, &keyframes, total_duration, self.tolerance))
+    }
+
+    /// 删除指定片段留下其余片段，片段会先排序合并并截到视频时长
     ///
     /// Examples
     ///
     /// ```
-    /// use bili_video::Remover;
+    /// use bili_video::{Remover, Segment, SegmentError};
+    ///
+    /// let s = |a: f64, b: f64| Segment::new(a, b);
     ///
-    /// let parts = Remover::remove_segments(1000, vec![(0, 200), (800, 1100)]);
-    /// assert_eq!(parts, vec![(200, 800)]);
+    /// let parts = Remover::remove_segments(1000.0, vec![s(0.0, 200.0), s(800.0, 1100.0)]).unwrap();
+    /// assert_eq!(parts, vec![s(200.0, 800.0)]);
     ///
-    /// let parts = Remover::remove_segments(1000, vec![(0, 200)]);
-    /// assert_eq!(parts, vec![(200, 1000)]);
+    /// let parts = Remover::remove_segments(1000.0, vec![s(0.0, 200.0)]).unwrap();
+    /// assert_eq!(parts, vec![s(200.0, 1000.0)]);
     ///
-    /// let parts = Remover::remove_segments(1000, vec![(800, 1100)]);
-    /// assert_eq!(parts, vec![(0, 800)]);
+    /// let parts = Remover::remove_segments(1000.0, vec![s(800.0, 1100.0)]).unwrap();
+    /// assert_eq!(parts, vec![s(0.0, 800.0)]);
     ///
-    /// let parts = Remover::remove_segments(1000, vec![(15, 200), (800, 900)]);
-    /// assert_eq!(parts, vec![(0, 15), (200, 800), (900, 1000)]);
+    /// let parts = Remover::remove_segments(1000.0, vec![s(15.0, 200.0), s(800.0, 900.0)]).unwrap();
+    /// assert_eq!(parts, vec![s(0.0, 15.0), s(200.0, 800.0), s(900.0, 1000.0)]);
+    ///
+    /// let parts = Remover::remove_segments(1383.7, vec![s(0.0, 89.4)]).unwrap();
+    /// assert_eq!(parts, vec![s(89.4, 1383.7)]);
+    ///
+    /// // 乱序和重叠的片段
+    /// let parts = Remover::remove_segments(1000.0, vec![s(800.0, 900.0), s(100.0, 200.0), s(150.0, 300.0)]).unwrap();
+    /// assert_eq!(parts, vec![s(0.0, 100.0), s(300.0, 800.0), s(900.0, 1000.0)]);
+    ///
+    /// // 超出视频时长的片段被丢弃
+    /// let parts = Remover::remove_segments(1000.0, vec![s(0.0, 200.0), s(1200.0, 1300.0)]).unwrap();
+    /// assert_eq!(parts, vec![s(200.0, 1000.0)]);
+    ///
+    /// let err = Remover::remove_segments(1000.0, vec![s(200.0, 100.0)]).unwrap_err();
+    /// assert_eq!(err, SegmentError::Reversed(s(200.0, 100.0)));
     /// ```
-    pub fn remove_segments(video_length: u64, segments: Vec<(u64, u64)>) -> Vec<(u64, u64)> {
+    pub fn remove_segments(video_length: f64, segments: Vec<Segment>) -> Result<Vec<Segment>, SegmentError> {
         let mut remaining_segments = Vec::new();
-        let mut last_end = 0;
+        let mut last_end = 0.0;
 
-        for (start, end) in segments {
+        for Segment { start, end } in normalize_segments(&segments, video_length)? {
             if start > last_end {
-                remaining_segments.push((last_end, start));
+                remaining_segments.push(Segment::new(last_end, start));
             }
             last_end = end;
         }
 
         if last_end < video_length {
-            remaining_segments.push((last_end, video_length));
+            remaining_segments.push(Segment::new(last_end, video_length));
         }
 
-        remaining_segments
+        Ok(remaining_segments)
+    }
+
+    /// 保留指定片段，超出视频时长的部分被截掉或丢弃，顺序不变
+    ///
+    /// Examples
+    ///
+    /// ```
+    /// use bili_video::{Remover, Segment, SegmentError};
+    ///
+    /// let s = |a: f64, b: f64| Segment::new(a, b);
+    ///
+    /// let parts = Remover::keep_segments(1000.0, vec![s(600.0, 700.0), s(100.0, 200.0)]).unwrap();
+    /// assert_eq!(parts, vec![s(600.0, 700.0), s(100.0, 200.0)]);
+    ///
+    /// let parts = Remover::keep_segments(1000.0, vec![s(900.0, 1100.0), s(1200.0, 1300.0)]).unwrap();
+    /// assert_eq!(parts, vec![s(900.0, 1000.0)]);
+    ///
+    /// let err = Remover::keep_segments(1000.0, vec![s(200.0, 100.0)]).unwrap_err();
+    /// assert_eq!(err, SegmentError::Reversed(s(200.0, 100.0)));
+    /// ```
+    pub fn keep_segments(video_length: f64, segments: Vec<Segment>) -> Result<Vec<Segment>, SegmentError> {
+        clamp_segments(&segments, video_length)
     }
 
     pub fn output<P>(&self, to: P) -> Result<PathBuf>
         where P: AsRef<Path>
     {
-        // 获取视频的总时长（假设视频时长已知或可通过其他方式获得）
-        let total_duration = Video::from(&self.path)?.duration;
-        let leave_parts = Self::remove_segments(total_duration as u64, self.segments.clone());
+        let mut temps: Vec<PathBuf> = Vec::new();
+        let token = self.token.clone().unwrap_or_else(current_cancel_token);
+        let result = with_cancel_token(token.clone(), || self.output_parts(to.as_ref(), &token, &mut temps));
 
-        let mut ts_slice: Vec<PathBuf> = Vec::new();
-        for (index, part) in leave_parts.iter().enumerate() {
-            let to_part = to.as_ref().with_extension(format!("{}.mp4", index));
-            if self.with_quick {
-                cut_quick(&self.path, &to_part, part.0 as f64, (part.1 - part.0) as f64)?;
-            } else {
-                cut(&self.path, &to_part, part.0 as f64, (part.1 - part.0) as f64)?;
+        // 删除中间文件，失败或取消时一并删除未完成的输出
+        for temp in temps {
+            if temp.exists() {
+                let _ = fs::remove_file(temp);
             }
-
-            let ts = to_ts(&to_part, None)?;
-            fs::remove_file(to_part)?;
-
-            ts_slice.push(ts);
         }
+        if result.is_err() && to.as_ref().exists() {
+            let _ = fs::remove_file(to.as_ref());
+        }
+        result
+    }
 
-        concat(&ts_slice, &to.as_ref().to_path_buf())?;
+    fn output_parts(&self, to: &Path, token: &CancelToken, temps: &mut Vec<PathBuf>) -> Result<PathBuf> {
+        let cuts = self.plan()?;
 
-        for ts in ts_slice {
-            fs::remove_file(ts)?;
+        let mut ts_slice: Vec<PathBuf> = Vec::new();
+        for (index, part) in cuts.iter().enumerate() {
+            token.check()?;
+            let to_part = to.with_extension(format!("{}.mp4", index));
+            part.report(self.mode);
+            ts_slice.extend(part.run_ts(&self.path, &to_part, temps)?);
         }
 
-        Ok(to.as_ref().to_path_buf())
+        token.check()?;
+        concat(&ts_slice, &to.to_path_buf())?;
+
+        Ok(to.to_path_buf())
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::{
+        testing::fixture, with_executor, AudioStream, CancelToken, CutMode, ProcessError, Segment, SegmentError, Video,
+    };
+
+    use super::Remover;
+
+    #[test]
+    fn test_output() {
+        let (dir, from, rec) = fixture("remover");
+        let to = dir.join("to.mp4");
+
+        with_executor(rec.clone(), || {
+            let mut r = Remover::new(&from, vec![Segment::new(0.0, 20.0), Segment::new(40.0, 60.5)]);
+            r.with_quick(true).output(&to).unwrap();
+        });
+
+        let p = |x: &str| dir.join(x).to_str().unwrap().to_string();
+        let calls = rec.calls();
+        assert_eq!(calls.len(), 5);
+        assert_eq!(calls[0], vec![
+            "ffmpeg", "-ss", "00:00:20.000", "-t", "00:00:20.000", "-i", &p("from.mp4"),
+            "-c", "copy", &p("to.0.mp4"),
+        ]);
+        assert_eq!(calls[1], vec![
+            "ffmpeg", "-i", &p("to.0.mp4"),
+            "-c", "copy", "-bsf:v", "h264_mp4toannexb", "-f", "mpegts", &p("to.0.ts"),
+        ]);
+        assert_eq!(calls[2], vec![
+            "ffmpeg", "-ss", "00:01:00.500", "-t", "00:00:39.500", "-i", &p("from.mp4"),
+            "-c", "copy", &p("to.1.mp4"),
+        ]);
+        assert_eq!(calls[4][..5], ["ffmpeg", "-f", "concat", "-safe", "0"]);
+        assert_eq!(calls[4].last().unwrap(), &p("to.mp4"));
+
+        // 中间文件已删除
+        assert!(!dir.join("to.0.ts").exists());
+        assert!(!dir.join("to.1.mp4").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_keyframe_tolerance() {
+        let (dir, from, rec) = fixture("remover-keyframe");
+        let to = dir.join("to.mp4");
+        rec.add_keyframes(&from, (0..=10).map(|x| x as f64 * 10.0).collect());
+
+        with_executor(rec.clone(), || {
+            let mut r = Remover::new(&from, vec![Segment::new(0.0, 20.0), Segment::new(40.0, 60.5)]);
+            r.with_quick(true);
+
+            let cuts = r.plan().unwrap();
+            assert_eq!(cuts[1].effective, Segment::new(60.0, 100.0));
+            assert_eq!(cuts[1].mode, CutMode::Quick);
+
+            // 偏差 0.5 秒超过容差，第二段重新编码
+            r.set_tolerance(Some(0.2)).output(&to).unwrap();
+        });
+
+        let p = |x: &str| dir.join(x).to_str().unwrap().to_string();
+        let calls = rec.calls();
+        assert_eq!(calls[0], vec![
+            "ffmpeg", "-ss", "00:00:20.000", "-t", "00:00:20.000", "-i", &p("from.mp4"),
+            "-c", "copy", &p("to.0.mp4"),
+        ]);
+        assert_eq!(calls[2], vec![
+            "ffmpeg", "-ss", "00:01:00.500", "-t", "00:00:39.500", "-i", &p("from.mp4"),
+            "-copyts", &p("to.1.mp4"),
+        ]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_cancel_token() {
+        let (dir, from, rec) = fixture("remover-cancel");
+        let to = dir.join("to.mp4");
 
+        let token = CancelToken::new();
+        token.cancel();
+        with_executor(rec.clone(), || {
+            let mut r = Remover::new(&from, vec![Segment::new(0.0, 20.0)]);
+            let err = r.set_cancel_token(token).output(&to).unwrap_err();
+            assert_eq!(err.downcast_ref::<ProcessError>(), Some(&ProcessError::Cancelled));
+
+            // 取消一个任务不影响其他任务
+            Remover::new(&from, vec![Segment::new(0.0, 20.0)]).output(&to).unwrap();
+        });
+        assert!(rec.calls().len() > 1);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_keep_only() {
+        let (dir, from, rec) = fixture("remover-keep");
+
+        let mut r = Remover::new(&from, vec![Segment::new(60.0, 80.0), Segment::new(10.0, 20.0)]);
+        r.set_keep_only(true);
+        let cuts = with_executor(rec.clone(), || r.plan().unwrap());
+        let requested: Vec<Segment> = cuts.iter().map(|x| x.requested).collect();
+        assert_eq!(requested, vec![Segment::new(60.0, 80.0), Segment::new(10.0, 20.0)]);
+
+        // 超出视频时长的片段被丢弃
+        let mut r = Remover::new(&from, vec![Segment::new(10.0, 20.0), Segment::new(120.0, 130.0)]);
+        r.set_keep_only(true);
+        let dropped = with_executor(rec.clone(), || r.out_of_range().unwrap());
+        assert_eq!(dropped, vec![SegmentError::OutOfRange(Segment::new(120.0, 130.0), 100.0)]);
+        let cuts = with_executor(rec.clone(), || r.plan().unwrap());
+        let requested: Vec<Segment> = cuts.iter().map(|x| x.requested).collect();
+        assert_eq!(requested, vec![Segment::new(10.0, 20.0)]);
+
+        // 全部超出时没有需要保留的片段
+        let mut r = Remover::new(&from, vec![Segment::new(120.0, 130.0)]);
+        r.set_keep_only(true);
+        assert!(with_executor(rec.clone(), || r.plan()).is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_smart_cut() {
+        let (dir, from, rec) = fixture("remover-smart");
+        let to = dir.join("to.mp4");
+        rec.add_video(&from, Video {
+            duration: 100.0,
+            codec: Some("h264".into()),
+            profile: Some("High".into()),
+            level: Some(40),
+            format: Some("yuv420p".into()),
+            frame_rate: Some("25/1".into()),
+            audios: vec![AudioStream { codec: "aac".into(), channels: 2, sample_rate: 44100, ..Default::default() }],
+            ..Default::default()
+        });
+        rec.add_keyframes(&from, (0..=10).map(|x| x as f64 * 10.0).collect());
+
+        with_executor(rec.clone(), || {
+            let mut r = Remover::new(&from, vec![Segment::new(0.0, 20.0), Segment::new(40.0, 60.5)]);
+            r.set_mode(CutMode::Smart);
+
+            let cuts = r.plan().unwrap();
+            assert_eq!(cuts[0].copy, Some(Segment::new(20.0, 40.0)));
+            assert_eq!(cuts[1].copy, Some(Segment::new(70.0, 100.0)));
+
+            r.output(&to).unwrap();
+        });
+
+        let p = |x: &str| dir.join(x).to_str().unwrap().to_string();
+        let calls = rec.calls();
+        // 第一段两端都在关键帧上，只需要复制
+        assert_eq!(calls[0], vec![
+            "ffmpeg", "-ss", "00:00:20.000", "-t", "00:00:20.000", "-i", &p("from.mp4"),
+            "-c", "copy", &p("to.0.smart1.mp4"),
+        ]);
+        // 第二段开头重新编码到下一个关键帧
+        assert_eq!(calls[2], vec![
+            "ffmpeg", "-ss", "00:01:00.500", "-t", "00:00:09.500", "-i", &p("from.mp4"),
+            "-c:v", "libx264", "-preset", "veryfast", "-crf", "18", "-profile:v", "high", "-level", "4",
+            "-pix_fmt", "yuv420p", "-r", "25/1", "-c:a", "aac", "-ar", "44100", "-ac", "2",
+            &p("to.1.smart0.mp4"),
+        ]);
+        assert_eq!(calls[4], vec![
+            "ffmpeg", "-ss", "00:01:10.000", "-t", "00:00:30.000", "-i", &p("from.mp4"),
+            "-c", "copy", &p("to.1.smart1.mp4"),
+        ]);
+        // 各部分的 ts 一起拼接，不再先合并每个片段
+        assert_eq!(calls.len(), 7);
+        assert_eq!(calls[6][..5], ["ffmpeg", "-f", "concat", "-safe", "0"]);
+        assert_eq!(calls[6].last().unwrap(), &p("to.mp4"));
+        assert!(!dir.join("to.1.smart0.ts").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_smart_cut_fallback() {
+        let (dir, from, rec) = fixture("remover-smart-vp9");
+        let to = dir.join("to.mp4");
+        rec.add_video(&from, Video { duration: 100.0, codec: Some("vp9".into()), ..Default::default() });
+        rec.add_keyframes(&from, (0..=10).map(|x| x as f64 * 10.0).collect());
+
+        with_executor(rec.clone(), || {
+            let mut r = Remover::new(&from, vec![Segment::new(0.0, 20.0), Segment::new(40.0, 60.5)]);
+            r.set_mode(CutMode::Smart).output(&to).unwrap();
+        });
+
+        // 没有可以转为 ts 的编码器，整段重新编码
+        let p = |x: &str| dir.join(x).to_str().unwrap().to_string();
+        let calls = rec.calls();
+        assert_eq!(calls[0], vec![
+            "ffmpeg", "-ss", "00:00:20.000", "-t", "00:00:20.000", "-i", &p("from.mp4"),
+            "-copyts", &p("to.0.mp4"),
+        ]);
+        assert_eq!(calls.len(), 5);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
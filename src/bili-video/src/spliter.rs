@@ -1,14 +1,38 @@
-use std::path::{Path, PathBuf};
-use anyhow::{Result};
+use std::{fs, path::{Path, PathBuf}};
+use anyhow::{anyhow, Result};
 
-use crate::{cut, cut_quick, Video};
+use crate::{
+    current_cancel_token, executor, find_boundary, format_timestamp, loudnorm::loudnorm_filter, plan_cuts,
+    twopass::encode_to_size, with_cancel_token, BoundaryMode, CancelToken, Cut, CutMode, Segment, StreamAction,
+    TranscodeProfile,
+};
 
+/// 默认在理想分割点前后多少秒内查找场景切换或静音
+const BOUNDARY_WINDOW: f64 = 10.0;
+
+
+/// 分割方式
+#[derive(Debug, Clone, PartialEq)]
+pub enum SplitBy {
+    /// 等分为指定数量
+    Parts(usize),
+    /// 等分为最少的部分，每一部分不超过指定时长（秒）
+    MaxDuration(f64),
+    /// 在指定的时间点分割
+    Timestamps(Vec<f64>),
+}
 
 #[derive(Debug)]
 pub struct Spliter {
     from: PathBuf,
-    parts: usize,
-    with_quick: bool,
+    by: SplitBy,
+    mode: CutMode,
+    tolerance: Option<f64>,
+    max_size: Option<f64>,
+    size_profile: Option<TranscodeProfile>,
+    boundary: BoundaryMode,
+    boundary_window: f64,
+    token: Option<CancelToken>,
 }
 
 impl Spliter {
@@ -17,47 +41,221 @@ impl Spliter {
     {
         Self {
             from: from.as_ref().to_path_buf(),
-            parts: 4,
-            with_quick: false,
+            by: SplitBy::Parts(4),
+            mode: CutMode::Accurate,
+            tolerance: None,
+            max_size: None,
+            size_profile: None,
+            boundary: BoundaryMode::Fixed,
+            boundary_window: BOUNDARY_WINDOW,
+            token: None,
         }
     }
 
     pub fn set_parts(&mut self, parts: usize) -> &mut Self {
-        self.parts = parts;
+        self.by = SplitBy::Parts(parts);
+        self
+    }
+
+    /// 按最大时长（秒）分割，数量由视频时长决定
+    pub fn set_max_duration(&mut self, duration: f64) -> &mut Self {
+        self.by = SplitBy::MaxDuration(duration);
+        self
+    }
+
+    /// 在指定的时间点（秒）分割，超出视频时长的时间点会被忽略
+    ///
+    /// 时间点同样按 `set_boundary` 对齐；`set_max_size` 只能和 `set_size_profile` 一起使用
+    pub fn set_timestamps(&mut self, timestamps: Vec<f64>) -> &mut Self {
+        self.by = SplitBy::Timestamps(timestamps);
+        self
+    }
+
+    pub fn set_split_by(&mut self, by: SplitBy) -> &mut Self {
+        self.by = by;
         self
     }
 
     pub fn with_quick(&mut self, f: bool) -> &mut Self {
-        self.with_quick = f;
+        self.mode = if f { CutMode::Quick } else { CutMode::Accurate };
+        self
+    }
+
+    pub fn set_mode(&mut self, mode: CutMode) -> &mut Self {
+        self.mode = mode;
         self
     }
 
+    /// 快速模式下允许的关键帧偏差（秒），超过时该部分重新编码
+    pub fn set_tolerance(&mut self, tolerance: Option<f64>) -> &mut Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// 每一部分的最大大小（MB），分割数量不足时自动增加
+    pub fn set_max_size(&mut self, max_size: Option<f64>) -> &mut Self {
+        self.max_size = max_size;
+        self
+    }
+
+    /// 设置后每一部分按该转码配置两遍编码到最大大小，需要同时设置 `set_max_size`
+    pub fn set_size_profile(&mut self, profile: Option<TranscodeProfile>) -> &mut Self {
+        self.size_profile = profile;
+        self
+    }
+
+    /// 分割点对齐到场景切换或静音
+    pub fn set_boundary(&mut self, boundary: BoundaryMode) -> &mut Self {
+        self.boundary = boundary;
+        self
+    }
+
+    /// 在理想分割点前后多少秒内查找，默认 10 秒
+    pub fn set_boundary_window(&mut self, window: f64) -> &mut Self {
+        self.boundary_window = window;
+        self
+    }
+
+    /// 只检查指定的取消令牌，默认为当前线程生效的令牌
+    pub fn set_cancel_token(&mut self, token: CancelToken) -> &mut Self {
+        self.token = Some(token);
+        self
+    }
+
+    /// 每一部分的截取计划，快速模式下包含对齐关键帧后的实际边界
+    pub fn plan(&self) -> Result<Vec<Cut>> {
+        let e = executor();
+        // 获取视频的总时长（假设视频时长已知或可通过其他方式获得）
+        let video = e.probe(&self.from)?;
+        let total_duration = video.duration;
+
+        let segments = match &self.by {
+            SplitBy::Timestamps(_) if self.max_size.is_some() && self.size_profile.is_none() => {
+                return Err(anyhow!("指定时间点分割时不能自动增加数量，max_size 需要同时设置 size_profile"));
+            }
+            SplitBy::Timestamps(timestamps) => {
+                let mut points: Vec<f64> = timestamps
+                    .iter()
+                    .copied()
+                    .filter(|x| *x > 0.0 && *x < total_duration)
+                    .collect();
+                points.sort_by(|a, b| a.total_cmp(b));
+                points.dedup();
+                points.insert(0, 0.0);
+                points.push(total_duration);
+
+                // 查找范围不超过最短部分的三分之一，保证分割点不会交叉
+                let shortest = points.windows(2).map(|x| x[1] - x[0]).fold(f64::INFINITY, f64::min);
+                self.align_points(&mut points, self.boundary_window.min(shortest / 3.0))?;
+                points.windows(2).map(|x| Segment::new(x[0], x[1])).collect()
+            }
+            SplitBy::MaxDuration(max) if *max <= 0.0 => return Err(anyhow!("最大时长必须大于 0")),
+            SplitBy::Parts(parts) => self.equal_segments(video.size, total_duration, *parts, None)?,
+            SplitBy::MaxDuration(max) => {
+                let parts = (total_duration / max).ceil().max(1.0) as usize;
+                self.equal_segments(video.size, total_duration, parts, Some(*max))?
+            }
+        };
+
+        let keyframes = match self.mode {
+            CutMode::Quick | CutMode::Smart => e.keyframes(&self.from)?,
+            CutMode::Accurate => Vec::new(),
+        };
+        Ok(plan_cuts(&segments, self.mode, &keyframes, total_duration, self.tolerance))
+    }
+
+    /// 等分为 `parts` 部分，分割点按设置对齐到场景切换或静音
+    ///
+    /// 设置了 `max_duration` 时对齐后每一部分也不会超过该时长
+    fn equal_segments(&self, size: u64, total_duration: f64, parts: usize, max_duration: Option<f64>) -> Result<Vec<Segment>> {
+        // 直接截取时按文件大小估算需要的数量，重新编码时每一部分都会压到最大大小
+        let mut parts = parts.max(1);
+        if let (Some(max_size), None) = (self.max_size, &self.size_profile) {
+            let need = (size as f64 / (max_size * 1024.0 * 1024.0)).ceil() as usize;
+            parts = parts.max(need);
+        }
+
+        // 计算每个部分的时长
+        let part_duration = total_duration / parts as f64;
+        let mut points: Vec<f64> = (0..=parts).map(|i| i as f64 * part_duration).collect();
+        // 浮点误差可能让最后一个点略小于时长
+        points[parts] = total_duration;
+
+        // 查找范围不超过每部分时长的三分之一，保证分割点不会交叉
+        let mut window = self.boundary_window.min(part_duration / 3.0);
+        if let Some(max) = max_duration {
+            window = window.min((max - part_duration) / 2.0);
+        }
+        self.align_points(&mut points, window)?;
+        Ok(points.windows(2).map(|x| Segment::new(x[0], x[1])).collect())
+    }
+
+    /// 中间的分割点按设置对齐到场景切换或静音，开头和结尾不变
+    fn align_points(&self, points: &mut [f64], window: f64) -> Result<()> {
+        if window <= 0.0 || points.len() < 3 {
+            return Ok(());
+        }
+        let last = points.len() - 1;
+        for point in points[1..last].iter_mut() {
+            if let Some(found) = find_boundary(&self.from, *point, window, self.boundary)? {
+                println!("分割点 {} 对齐到{} {}", format_timestamp(*point), self.boundary, format_timestamp(found));
+                *point = found;
+            }
+        }
+        Ok(())
+    }
+
     pub fn output<P>(&self, to: P) -> Result<Vec<PathBuf>>
         where P: AsRef<Path>
     {
-        // 获取视频的总时长（假设视频时长已知或可通过其他方式获得）
-        let total_duration = Video::from(&self.from)?.duration; // 你需要实现这个方法以获取视频时长
+        let mut output_paths = Vec::new();
+        let token = self.token.clone().unwrap_or_else(current_cancel_token);
+        let result = with_cancel_token(token.clone(), || self.output_parts(to.as_ref(), &token, &mut output_paths));
 
-        // 计算每个部分的时长
-        let part_duration = total_duration / self.parts as f64;
+        // 失败或取消时删除已经分割的视频
+        if result.is_err() {
+            for path in &output_paths {
+                if path.exists() {
+                    let _ = fs::remove_file(path);
+                }
+            }
+        }
+        result.map(|_| output_paths)
+    }
 
-        // 创建一个存储输出路径的向量
-        let mut output_paths = Vec::with_capacity(self.parts);
+    fn output_parts(&self, to: &Path, token: &CancelToken, output_paths: &mut Vec<PathBuf>) -> Result<()> {
+        let cuts = self.plan()?;
+        // 响度按整个源文件测量一次，每一部分共用
+        let filter = match (self.max_size, &self.size_profile) {
+            (Some(_), Some(profile)) => loudnorm_filter(&self.from, profile)?,
+            _ => None,
+        };
 
-        for i in 0..self.parts {
-            let start_time = i as f64 * part_duration;
-            let output_path = to.as_ref().with_extension(format!("P{}.mp4", i + 1));
+        for (i, part) in cuts.iter().enumerate() {
+            token.check()?;
+            let output_path = to.with_extension(format!("P{}.mp4", i + 1));
+            output_paths.push(output_path.clone());
 
             // 调用切割视频的方法
-            if self.with_quick {
-                cut_quick(&self.from, &output_path, start_time, part_duration)?;
-            } else {
-                cut(&self.from, &output_path, start_time, part_duration)?;
+            if let (Some(max_size), Some(profile)) = (self.max_size, &self.size_profile) {
+                let segment = Some(part.requested);
+                let audio = StreamAction::Encode;
+                encode_to_size(&self.from, &output_path, profile, segment, max_size, audio, filter.as_deref())?;
+                continue;
+            }
+            part.report(self.mode);
+            part.run(&self.from, &output_path)?;
+
+            // 关键帧和码率波动可能让部分视频超过最大大小
+            if let Some(max_size) = self.max_size {
+                let size = fs::metadata(&output_path).map(|x| x.len()).unwrap_or_default();
+                if size as f64 > max_size * 1024.0 * 1024.0 {
+                    println!("{:?} 超过 {}MB", output_path, max_size);
+                }
             }
-            output_paths.push(output_path);
         }
 
-        Ok(output_paths)
+        Ok(())
     }
 
 }
@@ -81,3 +279,127 @@ pub fn split<F, T>(from: F, to: T, parts: usize) -> Result<Vec<PathBuf>>
     let mut s = Spliter::new(from);
     s.set_parts(parts).output(to)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::{testing::fixture, with_executor, BoundaryMode, CutMode, Segment, TranscodeProfile, Video};
+
+    use super::Spliter;
+
+    #[test]
+    fn test_output() {
+        let (dir, from, rec) = fixture("spliter");
+        rec.add_video(&from, Video { duration: 100.2, ..Default::default() });
+
+        let paths = with_executor(rec.clone(), || {
+            let mut s = Spliter::new(&from);
+            s.set_parts(4).output(dir.join("to")).unwrap()
+        });
+
+        let p = |x: &str| dir.join(x).to_str().unwrap().to_string();
+        assert_eq!(paths, (1..=4).map(|i| dir.join(format!("to.P{}.mp4", i))).collect::<Vec<_>>());
+        assert_eq!(rec.calls(), vec![
+            vec!["ffmpeg", "-ss", "00:00:00.000", "-t", "00:00:25.050", "-i", &p("from.mp4"), "-copyts", &p("to.P1.mp4")],
+            vec!["ffmpeg", "-ss", "00:00:25.050", "-t", "00:00:25.050", "-i", &p("from.mp4"), "-copyts", &p("to.P2.mp4")],
+            vec!["ffmpeg", "-ss", "00:00:50.100", "-t", "00:00:25.050", "-i", &p("from.mp4"), "-copyts", &p("to.P3.mp4")],
+            vec!["ffmpeg", "-ss", "00:01:15.150", "-t", "00:00:25.050", "-i", &p("from.mp4"), "-copyts", &p("to.P4.mp4")],
+        ]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_quick_snap() {
+        let (dir, from, rec) = fixture("spliter-snap");
+        rec.add_video(&from, Video { duration: 100.2, ..Default::default() });
+        rec.add_keyframes(&from, (0..=10).map(|x| x as f64 * 10.0).collect());
+
+        let cuts = with_executor(rec.clone(), || {
+            let mut s = Spliter::new(&from);
+            s.set_parts(4).set_mode(CutMode::Quick).plan().unwrap()
+        });
+
+        let effective: Vec<Segment> = cuts.iter().map(|x| x.effective).collect();
+        assert_eq!(effective, vec![
+            Segment::new(0.0, 30.0),
+            Segment::new(30.0, 50.0),
+            Segment::new(50.0, 80.0),
+            Segment::new(80.0, 100.2),
+        ]);
+        assert!(cuts.iter().all(|x| x.mode == CutMode::Quick));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_scene_boundary() {
+        let (dir, from, rec) = fixture("spliter-scene");
+        rec.set_stderr("[Parsed_showinfo_1 @ 0x1] n:   0 pts: 7500 pts_time:7.5 duration: 40");
+
+        let cuts = with_executor(rec.clone(), || {
+            let mut s = Spliter::new(&from);
+            s.set_parts(2).set_boundary(BoundaryMode::Scene).plan().unwrap()
+        });
+
+        let calls = rec.calls();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0][1..5], ["-ss", "00:00:40.000", "-t", "00:00:20.000"]);
+        assert!(calls[0].contains(&"select='gt(scene,0.3)',showinfo".to_string()));
+        let requested: Vec<Segment> = cuts.iter().map(|x| x.requested).collect();
+        assert_eq!(requested, vec![Segment::new(0.0, 47.5), Segment::new(47.5, 100.0)]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_split_by() {
+        let (dir, from, rec) = fixture("spliter-by");
+        rec.add_video(&from, Video { duration: 1500.0, ..Default::default() });
+
+        let requested = |s: &Spliter| -> Vec<Segment> {
+            with_executor(rec.clone(), || s.plan().unwrap()).iter().map(|x| x.requested).collect()
+        };
+
+        // 每部分不超过 8 分钟，25 分钟分为 4 部分
+        let mut s = Spliter::new(&from);
+        s.set_max_duration(480.0);
+        assert_eq!(requested(&s), vec![
+            Segment::new(0.0, 375.0),
+            Segment::new(375.0, 750.0),
+            Segment::new(750.0, 1125.0),
+            Segment::new(1125.0, 1500.0),
+        ]);
+
+        // 指定时间点，忽略超出时长的时间点
+        s.set_timestamps(vec![900.0, 300.0, 2000.0]);
+        assert_eq!(requested(&s), vec![
+            Segment::new(0.0, 300.0),
+            Segment::new(300.0, 900.0),
+            Segment::new(900.0, 1500.0),
+        ]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_max_size() {
+        let (dir, from, rec) = fixture("spliter-size");
+        rec.add_video(&from, Video { duration: 600.0, size: 250 * 1024 * 1024, ..Default::default() });
+
+        let mut s = Spliter::new(&from);
+        s.set_parts(2).set_max_size(Some(100.0));
+        let cuts = with_executor(rec.clone(), || s.plan().unwrap());
+        assert_eq!(cuts.len(), 3);
+
+        // 重新编码时保持数量，每一部分两遍编码
+        s.set_size_profile(Some(TranscodeProfile::default()));
+        with_executor(rec.clone(), || s.output(dir.join("to")).unwrap());
+        let calls = rec.calls();
+        assert_eq!(calls.len(), 4);
+        assert_eq!(calls[0][..8], ["ffmpeg", "-y", "-ss", "00:00:00.000", "-t", "00:05:00.000", "-i", from.to_str().unwrap()]);
+        assert!(calls[0].windows(2).any(|x| x == ["-pass", "1"]));
+        assert!(calls[1].windows(2).any(|x| x == ["-pass", "2"]));
+        // 5 分钟 100MB，减去 319k 音频
+        assert!(calls[1].windows(2).any(|x| x == ["-b:v", "2421k"]));
+        assert_eq!(calls[3].last().unwrap(), dir.join("to.P2.mp4").to_str().unwrap());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,18 @@
+//! 测试共用的夹具
+use std::{env, fs, path::PathBuf, sync::Arc};
+
+use crate::{RecordingExecutor, Video};
+
+/// 在临时目录 `bili-video-{name}-test` 中创建空的 `from.mp4`
+///
+/// 返回目录、源文件和已记录 100 秒源视频的执行器，需要其他视频信息时再次调用 `add_video`
+pub fn fixture(name: &str) -> (PathBuf, PathBuf, Arc<RecordingExecutor>) {
+    let dir = env::temp_dir().join(format!("bili-video-{}-test", name));
+    fs::create_dir_all(&dir).unwrap();
+    let from = dir.join("from.mp4");
+    fs::write(&from, "").unwrap();
+
+    let rec = Arc::new(RecordingExecutor::new());
+    rec.add_video(&from, Video { duration: 100.0, ..Default::default() });
+    (dir, from, rec)
+}
@@ -0,0 +1,155 @@
+use std::{fs, path::Path};
+
+use anyhow::{anyhow, Result};
+
+use crate::{
+    executor, loudnorm::loudnorm_filter, profile::parse_bitrate, FfmpegCommand, Segment, StreamAction, TranscodeProfile,
+};
+
+/// 1 MB 的字节数
+const MB: f64 = 1024.0 * 1024.0;
+
+/// 预留给容器封装的比例
+const OVERHEAD: f64 = 0.02;
+
+/// 没有音频码率信息时使用的码率
+const DEFAULT_AUDIO_BITRATE: u64 = 128_000;
+
+/// 根据目标大小计算视频码率（bit/s）
+///
+/// 预留 2% 给容器封装，再减去音频码率
+///
+/// Examples
+///
+/// ```
+/// use bili_video::target_bitrate;
+///
+/// // 10 分钟压到 100 MB
+/// let rate = target_bitrate(100.0, 600.0, 128_000).unwrap();
+/// assert_eq!(rate / 1000, 1242);
+///
+/// assert!(target_bitrate(1.0, 600.0, 128_000).is_err());
+/// ```
+pub fn target_bitrate(size_mb: f64, duration: f64, audio_bitrate: u64) -> Result<u64> {
+    if duration <= 0.0 {
+        return Err(anyhow!("视频时长为 0，无法计算码率"));
+    }
+    let total = size_mb * MB * 8.0 * (1.0 - OVERHEAD) / duration;
+    let video = total - audio_bitrate as f64;
+    if video <= 0.0 {
+        return Err(anyhow!("目标大小 {}MB 过小，音频已占满码率", size_mb));
+    }
+    Ok(video as u64)
+}
+
+/// 两遍编码，把视频压缩到指定大小（MB）
+///
+/// 画面和音频参数使用转码配置，视频码率由目标大小计算，只支持 libx264
+///
+/// Examples
+///
+/// ```ignore
+/// use bili_video::{transcode_to_size, TranscodeProfile};
+///
+/// transcode_to_size("/tmp/movie.mp4", "/tmp/movie.small.mp4", &TranscodeProfile::default(), 2048.0).unwrap();
+/// ```
+pub fn transcode_to_size<F, T>(from: F, to: T, profile: &TranscodeProfile, size_mb: f64) -> Result<()>
+where
+    F: AsRef<Path>,
+    T: AsRef<Path>,
+{
+    let filter = loudnorm_filter(from.as_ref(), profile)?;
+    encode_to_size(from.as_ref(), to.as_ref(), profile, None, size_mb, StreamAction::Encode, filter.as_deref())
+}
+
+/// 两遍编码 `segment` 指定的片段，没有指定时编码整个视频
+///
+/// `filter` 是按整个源文件测量得到的响度滤镜，分割时每个片段共用
+pub(crate) fn encode_to_size(
+    from: &Path,
+    to: &Path,
+    profile: &TranscodeProfile,
+    segment: Option<Segment>,
+    size_mb: f64,
+    audio: StreamAction,
+    filter: Option<&str>,
+) -> Result<()> {
+    if profile.video_codec != "libx264" {
+        return Err(anyhow!("两遍编码只支持 libx264，当前为 {}", profile.video_codec));
+    }
+
+    let video = executor().probe(from)?;
+    let duration = segment.map_or(video.duration, |x| x.duration());
+    let audio_bitrate = match audio {
+        StreamAction::Encode => profile.audio_bitrate.as_deref().and_then(parse_bitrate),
+        StreamAction::Copy => video.audios.first().and_then(|x| x.bit_rate),
+    };
+    let bitrate = target_bitrate(size_mb, duration, audio_bitrate.unwrap_or(DEFAULT_AUDIO_BITRATE))?;
+    let bitrate = format!("{}k", bitrate / 1000);
+    println!("{:?} 两遍编码，目标 {}MB，视频码率 {}", to, size_mb, bitrate);
+
+    let filter = match audio {
+        StreamAction::Encode => filter,
+        StreamAction::Copy => None,
+    };
+    let passlog = to.with_extension("passlog");
+    let result = run_passes(from, to, profile, segment, duration, &bitrate, &passlog, audio, filter);
+
+    // x264 的统计文件
+    let passlog = passlog.to_string_lossy();
+    for ext in ["-0.log", "-0.log.mbtree"] {
+        let path = format!("{}{}", passlog, ext);
+        if Path::new(&path).exists() {
+            let _ = fs::remove_file(path);
+        }
+    }
+    result
+}
+
+#[allow(clippy::too_many_arguments)]
+fn run_passes(
+    from: &Path,
+    to: &Path,
+    profile: &TranscodeProfile,
+    segment: Option<Segment>,
+    duration: f64,
+    bitrate: &str,
+    passlog: &Path,
+    audio: StreamAction,
+    filter: Option<&str>,
+) -> Result<()> {
+    let passlog = passlog.to_string_lossy();
+    let null = if cfg!(windows) { "NUL" } else { "/dev/null" };
+
+    for pass in ["1", "2"] {
+        let mut cmd = FfmpegCommand::new();
+        cmd.overwrite();
+        let input = cmd.input(from);
+        if let Some(segment) = segment {
+            input.seek(segment.start).duration(segment.duration());
+        }
+
+        let output = cmd.output(if pass == "1" { Path::new(null) } else { to });
+        output
+            .video_codec(&profile.video_codec)
+            .option("-preset", &profile.preset)
+            .option("-b:v", bitrate)
+            .option("-pass", pass)
+            .option("-passlogfile", &passlog);
+        profile.apply_picture(output);
+        if pass == "1" {
+            // 第一遍只统计视频
+            output.flag("-an").format("null");
+        } else {
+            match audio {
+                StreamAction::Copy => output.audio_codec("copy"),
+                StreamAction::Encode => profile.apply_audio(output),
+            };
+            if let Some(filter) = filter {
+                output.audio_filter(filter);
+            }
+        }
+        cmd.set_total(Some(duration)).run()?;
+    }
+    Ok(())
+}